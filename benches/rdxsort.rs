@@ -0,0 +1,47 @@
+//! Compares `rdxsort` against the standard library sorts, reporting the
+//! throughput in elements per second.
+//!
+//! Run with `cargo bench --bench rdxsort`.
+use contest_algorithms::range_query::radix_tree::{Rdx, RdxSort};
+use contest_algorithms::rng::SmallRng;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+const SIZES: [usize; 4] = [100, 1_000, 10_000, 100_000];
+
+fn bench_type<T, F>(c: &mut Criterion, name: &str, gen: F)
+where
+    T: Rdx + Ord + Clone,
+    F: Fn(&mut SmallRng) -> T,
+{
+    for &n in SIZES.iter() {
+        let mut rng = SmallRng::new(n as u64);
+        let data: Vec<T> = (0..n).map(|_| gen(&mut rng)).collect();
+        let mut group = c.benchmark_group(format!("{}/{}", name, n));
+        group.throughput(Throughput::Elements(n as u64));
+
+        group.bench_function("rdxsort", |b| {
+            b.iter_batched_ref(|| data.clone(), |v| v.rdxsort(), BatchSize::LargeInput)
+        });
+        group.bench_function("sort", |b| {
+            b.iter_batched_ref(|| data.clone(), |v| v.sort(), BatchSize::LargeInput)
+        });
+        group.bench_function("sort_unstable", |b| {
+            b.iter_batched_ref(
+                || data.clone(),
+                |v| v.sort_unstable(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.finish();
+    }
+}
+
+fn bench_rdxsort(c: &mut Criterion) {
+    bench_type(c, "u8", |rng| rng.next_u32() as u8);
+    bench_type(c, "u32", |rng| rng.next_u32());
+    bench_type(c, "u64", |rng| rng.next_u64());
+    bench_type(c, "i32", |rng| rng.next_u32() as i32);
+}
+
+criterion_group!(benches, bench_rdxsort);
+criterion_main!(benches);
@@ -1,50 +1,178 @@
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::ptr::NonNull;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorMsg {
     Empty,
     Full,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RingBufferMode {
-    Override=0,
-    WriteNew
+    Override,
+    WriteNew,
+    /// A full buffer doubles its capacity instead of rejecting or evicting.
+    Grow,
+    /// Backpressure that gives way to a stalled reader. A write to a full
+    /// buffer fails as in WriteNew until this many writes, counting the
+    /// failed ones and the current one, have been attempted since the last
+    /// read; from the watermark on, it evicts the oldest element as in
+    /// Override. Writes to a buffer with free space always succeed.
+    OverrideAbove(usize),
 }
 
-pub struct RingBuffer<T> {
+/// Source of the memory backing a `RingBuffer`.
+///
+/// # Safety
+///
+/// `alloc` must return a block that is valid for `layout` (or null on
+/// failure) and stays valid until it is passed back to `dealloc`.
+pub unsafe trait RingAllocator {
+    /// Allocates a block for `layout`.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must have a non-zero size.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Frees a block previously returned by `alloc`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `alloc` on this allocator with the
+    /// same `layout`, and not freed since.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+}
+
+/// Allocates from the global allocator.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultAllocator;
+
+unsafe impl RingAllocator for DefaultAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc::alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::alloc::dealloc(ptr, layout)
+    }
+}
+
+/// Running counters of the traffic through a `RingBuffer`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RingStats {
+    /// Elements ever written.
+    pub total_written: u64,
+    /// Elements ever taken out by the reading methods.
+    pub total_read: u64,
+    /// Elements evicted to make room for a write while in `Override` or
+    /// `OverrideAbove` mode.
+    pub overwrites: u64,
+}
+
+pub struct RingBuffer<T, A: RingAllocator = DefaultAllocator> {
     buffer: *mut T,
     capacity: isize,
     read_offset: usize,
     write_offset: usize,
     mode: RingBufferMode,
+    alloc: A,
+    stats: RingStats,
+    /// Writes attempted since the last read, for `OverrideAbove`.
+    writes_since_read: usize,
 }
 
 impl<T> RingBuffer<T> {
     pub fn new(capacity: usize, mode: RingBufferMode) -> Self {
+        Self::new_in(capacity, mode, DefaultAllocator)
+    }
+}
+
+impl<T, A: RingAllocator> RingBuffer<T, A> {
+    /// Like `new`, but takes the buffer's memory from `alloc`.
+    pub fn new_in(capacity: usize, mode: RingBufferMode, alloc: A) -> Self {
         assert_ne!(capacity, 0);
-        let align = std::mem::align_of::<T>();
-        let element_size = std::mem::size_of::<T>();
-        let layout = std::alloc::Layout::from_size_align(element_size * capacity, align)
-            .expect("construction fail");
-        let ptr = unsafe { std::alloc::alloc(layout) } as *mut T;
+        let layout = Layout::array::<T>(capacity).expect("construction fail");
+        let ptr = if layout.size() == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            let ptr = unsafe { alloc.alloc(layout) } as *mut T;
+            if ptr.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+            ptr
+        };
 
         RingBuffer {
             capacity: capacity as isize,
             buffer: ptr,
             read_offset: 0,
             write_offset: 0,
-            mode: mode,
+            mode,
+            alloc,
+            stats: RingStats::default(),
+            writes_since_read: 0,
         }
     }
 
-    fn overwrite(&mut self, element: T) {
-        match self.mode {
-            RingBufferMode::Override => {
-                if self.is_full() {
-                    let _ = self.read();
-                }
-            }
-            RingBufferMode::WriteNew => {
-                self.write(element);
-            }
+    /// Writes an element regardless of the mode. If the buffer was full, the
+    /// oldest element is evicted to make room and returned. The eviction only
+    /// counts towards `RingStats::overwrites` in the overriding modes.
+    pub fn push_overwrite(&mut self, element: T) -> Option<T> {
+        let evicted = if self.is_full() { self.pop() } else { None };
+        let overriding = matches!(
+            self.mode,
+            RingBufferMode::Override | RingBufferMode::OverrideAbove(_)
+        );
+        if evicted.is_some() && overriding {
+            self.stats.overwrites += 1;
         }
+        self.push(element);
+        evicted
+    }
+
+    pub fn stats(&self) -> RingStats {
+        self.stats
+    }
+
+    fn len(&self) -> usize {
+        self.write_offset - self.read_offset
+    }
+
+    /// Returns the slot holding the `i`-th oldest element.
+    fn slot(&self, i: usize) -> *mut T {
+        unsafe { self.buffer.add((self.read_offset + i) % self.capacity as usize) }
+    }
+
+    /// Returns the buffered elements in FIFO order as up to two contiguous
+    /// runs: the first from the oldest element to the end of the allocation,
+    /// the second from its start. The second run is empty unless the
+    /// contents wrap around.
+    pub fn as_contiguous_slices(&self) -> (&[T], &[T]) {
+        let capacity = self.capacity as usize;
+        let len = self.len();
+        let first_len = len.min(capacity - self.read_offset);
+        unsafe {
+            (
+                core::slice::from_raw_parts(self.buffer.add(self.read_offset), first_len),
+                core::slice::from_raw_parts(self.buffer, len - first_len),
+            )
+        }
+    }
+
+    /// Copies the buffered elements into a `Vec`, oldest first.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let (front, back) = self.as_contiguous_slices();
+        let mut v = Vec::with_capacity(front.len() + back.len());
+        v.extend_from_slice(front);
+        v.extend_from_slice(back);
+        v
     }
 
     fn is_empty(&self) -> bool {
@@ -55,33 +183,144 @@ impl<T> RingBuffer<T> {
         self.write_offset - self.read_offset == self.capacity as usize
     }
 
+    /// Returns the oldest element, the one `read` would return next.
+    pub fn peek(&self) -> Option<&T> {
+        if self.is_empty() {
+            None
+        } else {
+            unsafe { Some(&*self.slot(0)) }
+        }
+    }
+
+    /// Returns the most recently written element without consuming it.
+    pub fn peek_back(&self) -> Option<&T> {
+        if self.is_empty() {
+            None
+        } else {
+            unsafe { Some(&*self.slot(self.len() - 1)) }
+        }
+    }
+
     pub fn read(&mut self) -> Result<T, ErrorMsg> {
+        let value = self.pop().ok_or(ErrorMsg::Empty)?;
+        self.stats.total_read += 1;
+        self.writes_since_read = 0;
+        Ok(value)
+    }
+
+    /// Pool-style name for `read`: hands out the oldest element, or None if
+    /// the buffer is empty.
+    pub fn take_oldest(&mut self) -> Option<T> {
+        self.read().ok()
+    }
+
+    /// Pool-style name for `write`: puts `item` back behind the others, so
+    /// items are reused in FIFO order.
+    pub fn return_slot(&mut self, item: T) -> Result<(), ErrorMsg> {
+        self.write(item)
+    }
+
+    /// Takes out the oldest element without counting it as read.
+    fn pop(&mut self) -> Option<T> {
         if self.is_empty() {
-            Err(ErrorMsg::Empty)
+            None
         } else {
             let value = unsafe {
-                let read_ptr = self.buffer.offset(self.read_offset as isize);
-                std::ptr::read(read_ptr)
+                let read_ptr = self.buffer.add(self.read_offset);
+                core::ptr::read(read_ptr)
             };
 
             self.read_offset += 1;
-            Ok(value)
+            self.realign();
+            Some(value)
         }
     }
 
+    /// Reads up to `max` of the oldest elements into the front of `dst`,
+    /// limited by the length of `dst`, and returns how many were read. Each
+    /// contiguous run is copied in one go.
+    pub fn read_up_to(&mut self, dst: &mut [T], max: usize) -> usize
+    where
+        T: Copy,
+    {
+        let count = max.min(dst.len()).min(self.len());
+        let (front, back) = self.as_contiguous_slices();
+        let from_front = count.min(front.len());
+        dst[..from_front].copy_from_slice(&front[..from_front]);
+        dst[from_front..count].copy_from_slice(&back[..count - from_front]);
+        self.read_offset += count;
+        self.realign();
+        self.stats.total_read += count as u64;
+        if count > 0 {
+            self.writes_since_read = 0;
+        }
+        count
+    }
+
+    /// Writes an element. When the buffer is full, Override mode evicts the
+    /// oldest element while WriteNew mode fails.
     pub fn write(&mut self, element: T) -> Result<(), ErrorMsg> {
+        self.write_evicting(element).map(drop)
+    }
+
+    /// Writes like `write`, returning the element evicted to make room.
+    fn write_evicting(&mut self, element: T) -> Result<Option<T>, ErrorMsg> {
+        self.writes_since_read += 1;
         if self.is_full() {
-            Err(ErrorMsg::Full)
+            match self.mode {
+                RingBufferMode::Override => Ok(self.push_overwrite(element)),
+                RingBufferMode::OverrideAbove(watermark) if self.writes_since_read >= watermark => {
+                    Ok(self.push_overwrite(element))
+                }
+                RingBufferMode::WriteNew | RingBufferMode::OverrideAbove(_) => Err(ErrorMsg::Full),
+                RingBufferMode::Grow => {
+                    self.grow();
+                    self.push(element);
+                    Ok(None)
+                }
+            }
         } else {
+            self.push(element);
+            Ok(None)
+        }
+    }
+
+    /// Doubles the capacity, moving the elements to the front of the new
+    /// allocation in FIFO order.
+    fn grow(&mut self) {
+        let old_capacity = self.capacity as usize;
+        let old_layout = Layout::array::<T>(old_capacity).unwrap();
+        let layout = Layout::array::<T>(2 * old_capacity).expect("capacity overflow");
+        if layout.size() != 0 {
+            let len = self.len();
             unsafe {
-                let write_ptr = self
-                    .buffer
-                    .offset((self.write_offset as isize) % (self.capacity as isize));
-                std::ptr::write(write_ptr, element);
+                let ptr = self.alloc.alloc(layout) as *mut T;
+                if ptr.is_null() {
+                    alloc::alloc::handle_alloc_error(layout);
+                }
+                let (front, back) = self.as_contiguous_slices();
+                core::ptr::copy_nonoverlapping(front.as_ptr(), ptr, front.len());
+                core::ptr::copy_nonoverlapping(back.as_ptr(), ptr.add(front.len()), back.len());
+                self.alloc.dealloc(self.buffer as *mut u8, old_layout);
+                self.buffer = ptr;
             }
-            self.write_offset += 1;
-            Ok(())
+            self.read_offset = 0;
+            self.write_offset = len;
+        }
+        self.capacity *= 2;
+    }
+
+    /// Writes an element into the free slot after the newest one.
+    /// The buffer must not be full.
+    fn push(&mut self, element: T) {
+        unsafe {
+            let write_ptr = self
+                .buffer
+                .add(self.write_offset % self.capacity as usize);
+            core::ptr::write(write_ptr, element);
         }
+        self.write_offset += 1;
+        self.stats.total_written += 1;
     }
     // under construction
     // pub fn remove(&mut self, element: T) -> Result<(),ErrorMsg>{
@@ -90,6 +329,20 @@ impl<T> RingBuffer<T> {
     //     }
     // }
 
+    /// Makes the element `n` places from the front the oldest one, moving the
+    /// `n` oldest elements behind the newest, wrapping `n` modulo the length.
+    /// Only the offsets change, which is why the buffer must be full: then
+    /// every slot is occupied and the FIFO order is just a rotation of them.
+    ///
+    /// Panics if the buffer is not full.
+    pub fn rotate_left(&mut self, n: usize) {
+        assert!(self.is_full(), "rotate_left needs a full buffer");
+        let n = n % self.capacity as usize;
+        self.read_offset += n;
+        self.write_offset += n;
+        self.realign();
+    }
+
     fn realign(&mut self) {
         if self.read_offset >= self.capacity as usize {
             self.read_offset -= self.capacity as usize;
@@ -98,34 +351,723 @@ impl<T> RingBuffer<T> {
     }
 
     pub fn clear(&mut self) {
-        loop {
-            match self.read() {
-                Ok(_) => {}
-                _ => break,
+        while self.pop().is_some() {}
+        self.read_offset = 0;
+        self.write_offset = 0;
+    }
+}
+
+impl<T, A: RingAllocator> Drop for RingBuffer<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+        let layout = Layout::array::<T>(self.capacity as usize).unwrap();
+        if layout.size() != 0 {
+            unsafe { self.alloc.dealloc(self.buffer as *mut u8, layout) };
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for RingBuffer<T> {
+    /// Makes a full WriteNew buffer holding the elements of `v`, oldest
+    /// first. An empty `v` gives an empty buffer of capacity 1.
+    fn from(v: Vec<T>) -> Self {
+        let mut rb = RingBuffer::new(v.len().max(1), RingBufferMode::WriteNew);
+        for element in v {
+            rb.push(element);
+        }
+        rb
+    }
+}
+
+impl<T, A: RingAllocator> From<RingBuffer<T, A>> for Vec<T> {
+    /// Moves the elements out in FIFO order.
+    fn from(mut rb: RingBuffer<T, A>) -> Self {
+        let mut v = Vec::with_capacity(rb.len());
+        while let Some(element) = rb.pop() {
+            v.push(element);
+        }
+        v
+    }
+}
+
+/// Consuming iterator over the elements of a `RingBuffer`, oldest first.
+/// Elements not yet yielded are dropped along with the iterator.
+pub struct IntoIter<T, A: RingAllocator = DefaultAllocator> {
+    rb: RingBuffer<T, A>,
+}
+
+impl<T, A: RingAllocator> Iterator for IntoIter<T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rb.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.rb.len(), Some(self.rb.len()))
+    }
+}
+
+impl<T, A: RingAllocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: RingAllocator> IntoIterator for RingBuffer<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+
+    fn into_iter(self) -> IntoIter<T, A> {
+        IntoIter { rb: self }
+    }
+}
+
+impl<T: Clone, A: RingAllocator + Clone> Clone for RingBuffer<T, A> {
+    /// Copies the elements into a fresh allocation, keeping them at the same
+    /// slots and offsets as in `self`.
+    fn clone(&self) -> Self {
+        let mut rb = Self::new_in(self.capacity as usize, self.mode, self.alloc.clone());
+        rb.read_offset = self.read_offset;
+        rb.write_offset = self.read_offset;
+        for i in 0..self.len() {
+            rb.push(unsafe { (*self.slot(i)).clone() });
+        }
+        rb.stats = self.stats;
+        rb.writes_since_read = self.writes_since_read;
+        rb
+    }
+}
+
+impl<T: PartialEq, A: RingAllocator> PartialEq for RingBuffer<T, A> {
+    /// Compares the buffered elements in FIFO order, regardless of where they
+    /// sit in memory.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && (0..self.len()).all(|i| unsafe { *self.slot(i) == *other.slot(i) })
+    }
+}
+
+/// A `RingBuffer` of numbers that keeps the sum, minimum and maximum of its
+/// current contents. The sum is updated in O(1) on every write and removal;
+/// the minimum or maximum is recomputed only when the removed element was the
+/// extreme. To keep floating-point rounding from piling up, the sum is also
+/// recomputed then and after every `capacity` removals, which is O(1)
+/// amortized.
+pub struct AggregateRingBuffer<T> {
+    inner: RingBuffer<T>,
+    sum: f64,
+    min: Option<T>,
+    max: Option<T>,
+    removals: usize,
+}
+
+impl<T: Copy + PartialOrd + Into<f64>> AggregateRingBuffer<T> {
+    pub fn new(capacity: usize, mode: RingBufferMode) -> Self {
+        AggregateRingBuffer {
+            inner: RingBuffer::new(capacity, mode),
+            sum: 0.0,
+            min: None,
+            max: None,
+            removals: 0,
+        }
+    }
+
+    /// Writes like `RingBuffer::write`, accounting for any evicted element.
+    pub fn write(&mut self, element: T) -> Result<(), ErrorMsg> {
+        let evicted = self.inner.write_evicting(element)?;
+        self.sum += element.into();
+        let (smaller, larger) = match (self.min, self.max) {
+            (Some(min), Some(max)) => (element < min, element > max),
+            _ => (true, true),
+        };
+        if smaller {
+            self.min = Some(element);
+        }
+        if larger {
+            self.max = Some(element);
+        }
+        if let Some(evicted) = evicted {
+            self.forget(evicted);
+        }
+        Ok(())
+    }
+
+    pub fn read(&mut self) -> Result<T, ErrorMsg> {
+        let value = self.inner.read()?;
+        self.forget(value);
+        Ok(value)
+    }
+
+    /// Takes `value`, which has just left the buffer, out of the aggregates.
+    fn forget(&mut self, value: T) {
+        self.sum -= value.into();
+        self.removals += 1;
+        if self.min == Some(value)
+            || self.max == Some(value)
+            || self.removals >= self.inner.capacity as usize
+        {
+            self.recompute();
+        }
+    }
+
+    /// Recomputes all the aggregates from the current contents.
+    fn recompute(&mut self) {
+        let (front, back) = self.inner.as_contiguous_slices();
+        let mut values = front.iter().chain(back).cloned();
+        let first = values.next();
+        let (mut min, mut max) = (first, first);
+        let mut sum = first.map_or(0.0, Into::into);
+        for x in values {
+            sum += x.into();
+            if matches!(min, Some(m) if x < m) {
+                min = Some(x);
             }
-            self.read_offset = 0;
-            self.write_offset = 0;
+            if matches!(max, Some(m) if x > m) {
+                max = Some(x);
+            }
+        }
+        self.sum = sum;
+        self.min = min;
+        self.max = max;
+        self.removals = 0;
+    }
+
+    /// Returns the mean of the buffered elements, or None if there are none.
+    /// An `Option` rather than a NaN for the empty buffer, so that `mean`
+    /// agrees with `min` and `max`.
+    pub fn mean(&self) -> Option<f64> {
+        match self.inner.len() {
+            0 => None,
+            len => Some(self.sum / len as f64),
         }
     }
+
+    pub fn min(&self) -> Option<T> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.max
+    }
+
+    /// Returns the underlying buffer.
+    pub fn as_ring_buffer(&self) -> &RingBuffer<T> {
+        &self.inner
+    }
+}
+
+/// A `RingBuffer` that stamps each element with the time it was written, for
+/// measuring how long elements wait before being read.
+#[cfg(feature = "std")]
+pub struct TimestampRing<T> {
+    inner: RingBuffer<(Instant, T)>,
+}
+
+#[cfg(feature = "std")]
+impl<T> TimestampRing<T> {
+    pub fn new(capacity: usize, mode: RingBufferMode) -> Self {
+        TimestampRing {
+            inner: RingBuffer::new(capacity, mode),
+        }
+    }
+
+    /// Writes like `RingBuffer::write`, stamped with the current time.
+    pub fn write(&mut self, element: T) -> Result<(), ErrorMsg> {
+        self.inner.write((Instant::now(), element))
+    }
+
+    /// Reads the oldest element along with the time it was written.
+    pub fn read(&mut self) -> Result<(Instant, T), ErrorMsg> {
+        self.inner.read()
+    }
+
+    /// Returns how long ago the oldest buffered element was written, or None
+    /// if the buffer is empty.
+    pub fn oldest_age(&self) -> Option<Duration> {
+        self.inner.peek().map(|(written, _)| written.elapsed())
+    }
+
+    /// Returns the underlying buffer.
+    pub fn as_ring_buffer(&self) -> &RingBuffer<(Instant, T)> {
+        &self.inner
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
     #[test]
     fn test_rb() {
         let mut rb = RingBuffer::new(10, RingBufferMode::Override);
         for i in 1..=10 {
-            rb.write(i);
+            assert_eq!(rb.write(i), Ok(()));
         }
         for i in 1..=10 {
-            let val = rb.read().unwrap_or(0);
-            assert_eq!(val, i);
+            assert_eq!(rb.read(), Ok(i));
         }
+        assert_eq!(rb.read(), Err(ErrorMsg::Empty));
 
         let mut rb = RingBuffer::new(5, RingBufferMode::Override);
         for i in 1..=10 {
-            rb.write(i);
+            assert_eq!(rb.write(i), Ok(()));
+        }
+        assert_eq!(rb.to_vec(), vec![6, 7, 8, 9, 10]);
+        for i in 6..=10 {
+            assert_eq!(rb.read(), Ok(i));
+        }
+        assert_eq!(rb.read(), Err(ErrorMsg::Empty));
+
+        let mut rb = RingBuffer::new(5, RingBufferMode::WriteNew);
+        for i in 1..=5 {
+            assert_eq!(rb.write(i), Ok(()));
+        }
+        for i in 6..=10 {
+            assert_eq!(rb.write(i), Err(ErrorMsg::Full));
+        }
+        assert_eq!(rb.to_vec(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(rb.read(), Ok(1));
+        assert_eq!(rb.write(11), Ok(()));
+        assert_eq!(rb.to_vec(), vec![2, 3, 4, 5, 11]);
+    }
+
+    struct CountingAllocator {
+        allocs: Rc<Cell<usize>>,
+        deallocs: Rc<Cell<usize>>,
+    }
+
+    unsafe impl RingAllocator for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.allocs.set(self.allocs.get() + 1);
+            std::alloc::alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.deallocs.set(self.deallocs.get() + 1);
+            std::alloc::dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_new_in() {
+        let allocs = Rc::new(Cell::new(0));
+        let deallocs = Rc::new(Cell::new(0));
+        let alloc = CountingAllocator {
+            allocs: allocs.clone(),
+            deallocs: deallocs.clone(),
+        };
+        let drops = Rc::new(());
+        {
+            let mut rb = RingBuffer::new_in(4, RingBufferMode::Override, alloc);
+            for _ in 0..6 {
+                assert!(rb.write(drops.clone()).is_ok());
+            }
+            assert!(rb.read().is_ok());
+            assert_eq!(Rc::strong_count(&drops), 4);
+        }
+        assert_eq!(Rc::strong_count(&drops), 1);
+        assert_eq!(allocs.get(), 1);
+        assert_eq!(deallocs.get(), 1);
+    }
+
+    #[test]
+    fn test_push_overwrite() {
+        let mut rb = RingBuffer::new(3, RingBufferMode::WriteNew);
+        for i in 1..=3 {
+            assert_eq!(rb.push_overwrite(i), None);
+        }
+        assert_eq!(rb.push_overwrite(4), Some(1));
+        assert_eq!(rb.push_overwrite(5), Some(2));
+        // not an overwrite in the sense of the stats, as the mode is WriteNew
+        assert_eq!(rb.stats().overwrites, 0);
+        for i in 3..=5 {
+            assert_eq!(rb.read().ok(), Some(i));
+        }
+        assert!(rb.read().is_err());
+    }
+
+    #[test]
+    fn test_clone_eq() {
+        let mut rb = RingBuffer::new(4, RingBufferMode::Override);
+        for i in 1..=6 {
+            assert!(rb.write(i.to_string()).is_ok());
+        }
+        let mut copy = rb.clone();
+        assert!(copy == rb);
+        assert_ne!(copy.buffer, rb.buffer);
+
+        let mut shifted = RingBuffer::new(4, RingBufferMode::Override);
+        for i in 3..=6 {
+            assert!(shifted.write(i.to_string()).is_ok());
+        }
+        assert!(shifted == rb);
+
+        assert!(copy.write("7".to_string()).is_ok());
+        assert!(copy != rb);
+        assert_eq!(rb.read().ok(), Some("3".to_string()));
+        assert_eq!(copy.read().ok(), Some("4".to_string()));
+    }
+
+    #[test]
+    fn test_as_contiguous_slices() {
+        let mut rb = RingBuffer::new(5, RingBufferMode::Override);
+        assert_eq!(rb.as_contiguous_slices(), (&[][..], &[][..]));
+        for i in 1..=3 {
+            assert!(rb.write(i).is_ok());
+        }
+        assert_eq!(rb.as_contiguous_slices(), (&[1, 2, 3][..], &[][..]));
+
+        for i in 4..=8 {
+            assert!(rb.write(i).is_ok());
+        }
+        let (a, b) = rb.as_contiguous_slices();
+        assert_eq!((a, b), (&[4, 5][..], &[6, 7, 8][..]));
+        let mut out = [0; 5];
+        out[..a.len()].copy_from_slice(a);
+        out[a.len()..].copy_from_slice(b);
+        assert_eq!(out, [4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_grow() {
+        let mut rb = RingBuffer::new(4, RingBufferMode::Grow);
+        for i in 0..3 {
+            assert!(rb.write(i.to_string()).is_ok());
+        }
+        assert_eq!(rb.read().ok(), Some("0".to_string()));
+        for i in 3..100 {
+            assert!(rb.write(i.to_string()).is_ok());
+        }
+        assert_eq!(rb.capacity, 128);
+        for i in 1..100 {
+            assert_eq!(rb.read().ok(), Some(i.to_string()));
+        }
+        assert!(rb.read().is_err());
+
+        let mut units = RingBuffer::new(1, RingBufferMode::Grow);
+        for _ in 0..10 {
+            assert!(units.write(()).is_ok());
+        }
+        assert_eq!(units.len(), 10);
+    }
+
+    #[test]
+    fn test_read_up_to() {
+        let mut rb = RingBuffer::new(6, RingBufferMode::Override);
+        for i in 1..=9 {
+            assert!(rb.write(i).is_ok());
+        }
+        assert_eq!(rb.read().ok(), Some(4));
+        // 5, 6 sit at the end of the allocation and 7, 8, 9 at its start
+        assert_eq!(rb.as_contiguous_slices(), (&[5, 6][..], &[7, 8, 9][..]));
+        let mut dst = [0; 8];
+        assert_eq!(rb.read_up_to(&mut dst, 3), 3);
+        assert_eq!(dst[..3], [5, 6, 7]);
+        assert_eq!(rb.len(), 2);
+        assert_eq!(rb.read().ok(), Some(8));
+
+        assert!(rb.write(10).is_ok());
+        assert_eq!(rb.read_up_to(&mut dst[..1], 5), 1);
+        assert_eq!(dst[0], 9);
+        assert_eq!(rb.read_up_to(&mut dst, 5), 1);
+        assert_eq!(dst[0], 10);
+        assert_eq!(rb.read_up_to(&mut dst, 5), 0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut rb = RingBuffer::new(4, RingBufferMode::Override);
+        for i in 0..10 {
+            assert!(rb.write(i).is_ok());
+        }
+        assert_eq!(rb.read().ok(), Some(6));
+        let mut dst = [0; 2];
+        assert_eq!(rb.read_up_to(&mut dst, 2), 2);
+        assert_eq!(
+            rb.stats(),
+            RingStats {
+                total_written: 10,
+                total_read: 3,
+                overwrites: 6,
+            }
+        );
+        rb.clear();
+        assert_eq!(rb.stats().total_read, 3);
+
+        let mut rb = RingBuffer::new(2, RingBufferMode::WriteNew);
+        for i in 0..5 {
+            let _ = rb.write(i);
+        }
+        assert_eq!(rb.stats().total_written, 2);
+        assert_eq!(rb.stats().overwrites, 0);
+    }
+
+    #[test]
+    fn test_vec_conversions() {
+        let mut rb = RingBuffer::from(vec![1, 2, 3, 4]);
+        assert!(rb.write(5).is_err());
+        assert_eq!(rb.read().ok(), Some(1));
+        assert!(rb.write(5).is_ok());
+        assert_eq!(Vec::from(rb), vec![2, 3, 4, 5]);
+
+        let rb = RingBuffer::from(vec![1, 2, 3, 4]);
+        assert_eq!(Vec::from(rb), vec![1, 2, 3, 4]);
+
+        let drops = Rc::new(());
+        let mut rb = RingBuffer::from(vec![drops.clone(), drops.clone(), drops.clone()]);
+        assert!(rb.read().is_ok());
+        assert!(rb.write(drops.clone()).is_ok());
+        let v: Vec<_> = rb.into();
+        assert_eq!(v.len(), 3);
+        assert_eq!(Rc::strong_count(&drops), 4);
+        drop(v);
+        assert_eq!(Rc::strong_count(&drops), 1);
+
+        let empty: Vec<i32> = RingBuffer::from(Vec::new()).into();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_override_keeps_last_written() {
+        let mut rng = crate::rng::SmallRng::new(157);
+        for &capacity in [1, 2, 3, 7, 16].iter() {
+            let mut rb = RingBuffer::new(capacity, RingBufferMode::Override);
+            let mut written = vec![];
+            for _ in 0..1000 {
+                let value = rng.next_u32();
+                assert!(rb.write(value).is_ok());
+                written.push(value);
+                let kept = written.len().min(capacity);
+                assert_eq!(rb.to_vec(), written[written.len() - kept..]);
+            }
+
+            // with reads mixed in, the buffer holds the newest values not
+            // yet read, at most `capacity` of them
+            let mut model: VecDeque<u32> = rb.to_vec().into();
+            for _ in 0..1000 {
+                match rng.next_u32() % 4 {
+                    0 => assert_eq!(rb.read().ok(), model.pop_front()),
+                    _ => {
+                        let value = rng.next_u32();
+                        assert!(rb.write(value).is_ok());
+                        if model.len() == capacity {
+                            model.pop_front();
+                        }
+                        model.push_back(value);
+                    }
+                }
+                assert_eq!(rb.to_vec(), Vec::from(model.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_aggregate_ring_buffer() {
+        let mut rb = AggregateRingBuffer::new(3, RingBufferMode::Override);
+        assert_eq!((rb.mean(), rb.min(), rb.max()), (None, None, None));
+        for &x in [5, 1, 9].iter() {
+            assert!(rb.write(x).is_ok());
+        }
+        assert_eq!((rb.mean(), rb.min(), rb.max()), (Some(5.0), Some(1), Some(9)));
+
+        // evicts 5, then the minimum 1, then the maximum 9
+        for &x in [4, 6, 2].iter() {
+            assert!(rb.write(x).is_ok());
+        }
+        assert_eq!(rb.as_ring_buffer().to_vec(), vec![4, 6, 2]);
+        assert_eq!((rb.mean(), rb.min(), rb.max()), (Some(4.0), Some(2), Some(6)));
+        assert_eq!(rb.read().ok(), Some(4));
+        assert_eq!((rb.mean(), rb.min(), rb.max()), (Some(4.0), Some(2), Some(6)));
+        assert_eq!(rb.read().ok(), Some(6));
+        assert_eq!((rb.min(), rb.max()), (Some(2), Some(2)));
+        assert_eq!(rb.read().ok(), Some(2));
+        assert_eq!((rb.mean(), rb.min(), rb.max()), (None, None, None));
+
+        let mut full = AggregateRingBuffer::new(2, RingBufferMode::WriteNew);
+        assert!(full.write(1.5f32).is_ok());
+        assert!(full.write(2.5).is_ok());
+        assert!(full.write(100.0).is_err());
+        assert_eq!((full.mean(), full.max()), (Some(2.0), Some(2.5)));
+
+        let mut rng = crate::rng::SmallRng::new(164);
+        let mut rb = AggregateRingBuffer::new(8, RingBufferMode::Override);
+        let mut written = vec![];
+        for _ in 0..500 {
+            let x = (rng.next_u32() % 100) as u8;
+            assert!(rb.write(x).is_ok());
+            written.push(x);
+            let window = &written[written.len().saturating_sub(8)..];
+            let sum: u32 = window.iter().map(|&x| u32::from(x)).sum();
+            assert_eq!(rb.mean(), Some(f64::from(sum) / window.len() as f64));
+            assert_eq!(rb.min(), window.iter().min().cloned());
+            assert_eq!(rb.max(), window.iter().max().cloned());
+        }
+
+        // 1e17 + 1.0 rounds back to 1e17, so subtracting the evicted values
+        // from a running sum would leave 0 instead of 3
+        let mut rb = AggregateRingBuffer::new(3, RingBufferMode::Override);
+        for &x in [1e16, 1e17, 1.0, 1.0, 1.0].iter() {
+            assert!(rb.write(x).is_ok());
+        }
+        assert_eq!(rb.mean(), Some(1.0));
+
+        // a widening zigzag never evicts an extreme, so only the periodic
+        // recomputation bounds the rounding error
+        let mut rb = AggregateRingBuffer::new(4, RingBufferMode::Override);
+        let mut written = vec![];
+        for k in 0..100_000 {
+            let k = f64::from(k);
+            let x = if k % 2.0 == 0.0 { 1.37 * k + 0.1 } else { -1.41 * k - 0.3 };
+            assert!(rb.write(x).is_ok());
+            written.push(x);
+        }
+        let exact: f64 = written[written.len() - 4..].iter().sum::<f64>() / 4.0;
+        assert!((rb.mean().unwrap() - exact).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_rotate_left() {
+        let mut rb = RingBuffer::new(5, RingBufferMode::Override);
+        for i in 0..7 {
+            assert!(rb.write(i).is_ok());
         }
+        assert_eq!(rb.to_vec(), vec![2, 3, 4, 5, 6]);
+        rb.rotate_left(2);
+        assert_eq!(rb.to_vec(), vec![4, 5, 6, 2, 3]);
+        rb.rotate_left(13);
+        assert_eq!(rb.to_vec(), vec![2, 3, 4, 5, 6]);
+        rb.rotate_left(0);
+        assert!(rb.write(7).is_ok());
+        assert_eq!(rb.read().ok(), Some(3));
+        assert_eq!(rb.to_vec(), vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "rotate_left needs a full buffer")]
+    fn test_rotate_left_not_full() {
+        let mut rb = RingBuffer::new(3, RingBufferMode::WriteNew);
+        assert!(rb.write(1).is_ok());
+        rb.rotate_left(1);
+    }
+
+    #[test]
+    fn test_override_above() {
+        let mut rb = RingBuffer::new(10, RingBufferMode::OverrideAbove(8));
+        for i in 0..10 {
+            assert_eq!(rb.write(i), Ok(()));
+        }
+        assert_eq!(rb.read(), Ok(0));
+        assert_eq!(rb.write(10), Ok(()));
+        // below the watermark, writes to the full buffer push back
+        for i in 11..17 {
+            assert_eq!(rb.write(i), Err(ErrorMsg::Full));
+        }
+        assert_eq!(rb.to_vec(), (1..11).collect::<Vec<_>>());
+        assert_eq!(rb.stats().overwrites, 0);
+        // the eighth write since the read reaches it and overwrites
+        assert_eq!(rb.write(17), Ok(()));
+        assert_eq!(rb.write(18), Ok(()));
+        assert_eq!(rb.stats().overwrites, 2);
+        let expected: Vec<_> = (3..11).chain(17..19).collect();
+        assert_eq!(rb.to_vec(), expected);
+
+        // a read puts the buffer back below the watermark
+        assert_eq!(rb.read(), Ok(3));
+        assert_eq!(rb.write(19), Ok(()));
+        assert_eq!(rb.write(20), Err(ErrorMsg::Full));
+
+        let mut agg = AggregateRingBuffer::new(4, RingBufferMode::OverrideAbove(2));
+        for &x in [1, 9, 4, 7, 3].iter() {
+            assert_eq!(agg.write(x), Ok(()));
+        }
+        assert_eq!((agg.mean(), agg.min(), agg.max()), (Some(5.75), Some(3), Some(9)));
+    }
+
+    #[test]
+    fn test_peek_back() {
+        let mut rb = RingBuffer::new(3, RingBufferMode::Override);
+        assert_eq!(rb.peek_back(), None);
+        assert_eq!(rb.peek(), None);
+        for i in 1..=5 {
+            assert_eq!(rb.write(i), Ok(()));
+            assert_eq!(rb.peek_back(), Some(&i));
+        }
+        assert_eq!(rb.len(), 3);
+        assert_eq!(rb.peek(), Some(&3));
+        assert_eq!(rb.peek_back(), Some(&5));
+        assert_eq!(rb.len(), 3);
+        assert_eq!(rb.read(), Ok(3));
+        assert_eq!(rb.read(), Ok(4));
+        assert_eq!(rb.peek(), rb.peek_back());
+        assert_eq!(rb.read(), Ok(5));
+        assert_eq!(rb.peek_back(), None);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut rb = RingBuffer::new(4, RingBufferMode::Override);
+        for i in 0..10 {
+            assert_eq!(rb.write(i), Ok(()));
+        }
+        let it = rb.into_iter();
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.collect::<Vec<_>>(), vec![6, 7, 8, 9]);
+
+        let counter = Rc::new(());
+        let mut rb = RingBuffer::new(3, RingBufferMode::Override);
+        for _ in 0..5 {
+            assert_eq!(rb.write(counter.clone()), Ok(()));
+        }
+        assert_eq!(Rc::strong_count(&counter), 4);
+        let mut it = rb.into_iter();
+        drop(it.next());
+        assert_eq!(Rc::strong_count(&counter), 3);
+        drop(it);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_timestamp_ring() {
+        use std::thread::sleep;
+
+        let mut ring = TimestampRing::new(2, RingBufferMode::Override);
+        assert_eq!(ring.oldest_age(), None);
+        assert_eq!(ring.write("a"), Ok(()));
+        sleep(Duration::from_millis(5));
+        assert_eq!(ring.write("b"), Ok(()));
+        let first = ring.oldest_age().unwrap();
+        assert!(first >= Duration::from_millis(5));
+        sleep(Duration::from_millis(5));
+        let second = ring.oldest_age().unwrap();
+        assert!(second >= first + Duration::from_millis(5));
+
+        // reading "a" makes "b" the oldest, which is younger
+        let (written, value) = ring.read().unwrap();
+        assert_eq!(value, "a");
+        assert!(written.elapsed() >= second);
+        assert!(ring.oldest_age().unwrap() < written.elapsed());
+        assert_eq!(ring.read().map(|(_, value)| value), Ok("b"));
+        assert_eq!(ring.oldest_age(), None);
+    }
+
+    #[test]
+    fn test_take_oldest_return_slot() {
+        let mut pool = RingBuffer::new(3, RingBufferMode::WriteNew);
+        for name in ["a", "b", "c"].iter() {
+            assert_eq!(pool.return_slot(name.to_string()), Ok(()));
+        }
+        assert!(pool.return_slot("d".to_string()).is_err());
+
+        let mut order = vec![];
+        for _ in 0..6 {
+            let item = pool.take_oldest().unwrap();
+            order.push(item.clone());
+            assert_eq!(pool.return_slot(item), Ok(()));
+        }
+        assert_eq!(order, vec!["a", "b", "c", "a", "b", "c"]);
+
+        while pool.take_oldest().is_some() {}
+        assert_eq!(pool.take_oldest(), None);
+        assert_eq!(pool.stats().total_read, 9);
     }
 }
@@ -1,29 +1,57 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
+use std::sync::RwLock;
 use rand::*;
 
 type RealNode = Rc<RefCell<Node>>;
 type Link = Option<Rc<RefCell<Node>>>;
+type Entry = (u64, String);
 
 #[derive(Debug, Clone)]
 struct Node {
     data: String,
     next: Vec<Link>,
+    /// span[i] is how many entries ahead next[i] lies. A missing link counts
+    /// as pointing one past the last entry.
+    span: Vec<usize>,
     offset: u64,
 }
 
 impl Node {
     fn new(next: Vec<Link>, offset: u64, data: String) -> RealNode {
-        Rc::new(RefCell::new(Node { next, offset, data }))
+        let span = vec![1; next.len()];
+        Rc::new(RefCell::new(Node { next, span, offset, data }))
     }
 }
 
+/// Picks node heights in place of `random_level`, see `new_with_level_fn`.
+#[derive(Clone)]
+struct LevelFn(Rc<dyn Fn() -> usize>);
+
+impl fmt::Debug for LevelFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LevelFn")
+    }
+}
+
+/// Ordered map from offsets to data. Every offset is stored at most once:
+/// `append` and `insert` return false and leave the list unchanged when the
+/// offset is already present.
 #[derive(Debug, Clone)]
 pub struct SkipList {
     head: Link,
     tails: Vec<Link>,
     max_level: usize,
     length: u64,
+    /// Sorted offsets of every data value, kept only if the list was built
+    /// with `with_data_index`.
+    data_index: Option<HashMap<String, Vec<u64>>>,
+    level_fn: Option<LevelFn>,
+    /// Whether a level is added once `length` exceeds `2^max_level`, as in
+    /// lists built with `with_auto_levels`.
+    auto_levels: bool,
 }
 
 impl SkipList {
@@ -33,10 +61,87 @@ impl SkipList {
             tails: vec![None; level],
             max_level: level - 1,
             length: 0,
+            data_index: None,
+            level_fn: None,
+            auto_levels: false,
+        }
+    }
+
+    /// Like `new`, but also maintains a map from data to offsets so that
+    /// `offsets_for_data` does not have to scan the list.
+    pub fn with_data_index(level: usize) -> Self {
+        SkipList {
+            data_index: Some(HashMap::new()),
+            ..SkipList::new(level)
+        }
+    }
+
+    /// Like `new`, but the list gains a level whenever its length exceeds
+    /// `2^max_level`, so that new nodes can rise higher as it grows. Nodes
+    /// already linked keep their heights, and the list never loses levels.
+    pub fn with_auto_levels(level: usize) -> Self {
+        SkipList {
+            auto_levels: true,
+            ..SkipList::new(level)
+        }
+    }
+
+    /// Adds levels on top while an auto-scaling list is longer than
+    /// `2^max_level`. Only the head spans a new level at first.
+    fn grow_levels(&mut self) {
+        if !self.auto_levels {
+            return;
+        }
+        let head = match self.head {
+            Some(ref head) => head.clone(),
+            None => return,
+        };
+        while self.max_level < 63 && self.length > 1 << self.max_level {
+            let mut node = head.borrow_mut();
+            node.next.push(None);
+            // a missing link points one past the last entry
+            node.span.push(self.length as usize);
+            self.tails.push(Some(head.clone()));
+            self.max_level += 1;
+        }
+    }
+
+    fn index_add(&mut self, offset: u64, data: &str) {
+        if let Some(ref mut index) = self.data_index {
+            let offsets = index.entry(data.to_string()).or_default();
+            if let Err(pos) = offsets.binary_search(&offset) {
+                offsets.insert(pos, offset);
+            }
+        }
+    }
+
+    fn index_remove(&mut self, offset: u64, data: &str) {
+        if let Some(ref mut index) = self.data_index {
+            if let Some(offsets) = index.get_mut(data) {
+                if let Ok(pos) = offsets.binary_search(&offset) {
+                    offsets.remove(pos);
+                }
+                if offsets.is_empty() {
+                    index.remove(data);
+                }
+            }
+        }
+    }
+
+    /// Like `new`, but the level of each new node, counted from 0, is taken
+    /// from `f` instead of coin flips, capped at `level - 1`. The first node
+    /// still spans every level. Meant for testing degenerate shapes.
+    pub fn new_with_level_fn(level: usize, f: impl Fn() -> usize + 'static) -> Self {
+        SkipList {
+            level_fn: Some(LevelFn(Rc::new(f))),
+            ..SkipList::new(level)
         }
     }
 
     fn random_level(&self) -> usize {
+        if let Some(LevelFn(ref f)) = self.level_fn {
+            return f().min(self.max_level);
+        }
         let mut n = 0;
         while random::<bool>() && n < self.max_level {
             n += 1;
@@ -44,25 +149,91 @@ impl SkipList {
         n
     }
 
-    pub fn append(&mut self, offset: u64, data: String) {
+    /// Adds an entry after the last one. This is the fast path for offsets
+    /// arriving in increasing order; any other offset goes through `insert`.
+    /// Returns false if the offset is already present.
+    pub fn append(&mut self, offset: u64, data: String) -> bool {
+        if matches!(self.max_offset(), Some(last) if offset <= last) {
+            return self.insert(offset, data);
+        }
         let level = 1 + if self.head.is_none() {
             self.max_level
         } else {
             self.random_level()
         };
-            let node = Node::new(vec![None; level], offset, data);
-            for i in 0..level {
-                if let Some(old) = self.tails[i].take() {
-                    let next = &mut old.borrow_mut().next;
-                    next[i] = Some(node.clone());
-                }
-                self.tails[i] = Some(node.clone());
+        self.push_back(level, offset, data);
+        true
+    }
+
+    /// Links a new node of height `level` after the last one. The caller
+    /// guarantees that `offset` is above every stored offset.
+    fn push_back(&mut self, level: usize, offset: u64, data: String) {
+        self.index_add(offset, &data);
+        let node = Node::new(vec![None; level], offset, data);
+        for i in 0..level {
+            if let Some(old) = self.tails[i].take() {
+                let next = &mut old.borrow_mut().next;
+                next[i] = Some(node.clone());
             }
-            if self.head.is_none() {
-                self.head = Some(node.clone());
+            self.tails[i] = Some(node.clone());
+        }
+        for i in level..=self.max_level {
+            if let Some(ref tail) = self.tails[i] {
+                tail.borrow_mut().span[i] += 1;
             }
-            self.length += 1;
         }
+        if self.head.is_none() {
+            self.head = Some(node.clone());
+        }
+        self.length += 1;
+        self.grow_levels();
+    }
+
+    /// Rebuilds the links above level 0 so that every `2^i`-th entry
+    /// reaches level `i`, restoring the expected level sizes after
+    /// removals have thinned out the upper levels unevenly.
+    pub fn compact(&mut self) {
+        // the entries stay the same, so the index is set aside, not rebuilt
+        let data_index = self.data_index.take();
+        let level_fn = self.level_fn.take();
+        let auto_levels = self.auto_levels;
+        let list = std::mem::replace(self, SkipList::new(self.max_level + 1));
+        for (i, (offset, data)) in list.into_entries().into_iter().enumerate() {
+            let level = if i == 0 {
+                self.max_level
+            } else {
+                (i.trailing_zeros() as usize).min(self.max_level)
+            };
+            self.push_back(level + 1, offset, data);
+        }
+        self.data_index = data_index;
+        self.level_fn = level_fn;
+        self.auto_levels = auto_levels;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns the largest stored offset in O(1), read off the level 0 tail.
+    pub fn max_offset(&self) -> Option<u64> {
+        self.tails[0].as_ref().map(|tail| tail.borrow().offset)
+    }
+
+    /// Removes all entries. The nodes are unlinked one by one, so a long
+    /// list is not dropped through a deep chain of recursive drops.
+    pub fn clear(&mut self) {
+        let mut link = self.head.take();
+        while let Some(node) = link {
+            let next = std::mem::take(&mut node.borrow_mut().next);
+            link = next.into_iter().next().flatten();
+        }
+        self.tails = vec![None; self.max_level + 1];
+        self.length = 0;
+        if let Some(ref mut index) = self.data_index {
+            index.clear();
+        }
+    }
     
         fn max_level(&self) -> usize {
             self.max_level
@@ -72,69 +243,593 @@ impl SkipList {
             self.length
         }
     
-        fn level_path(&self) {
-            match self.head {
-                Some(ref head) => {
-                    let node = head.clone();
-                    for level in (0..=self.max_level()).rev() {
-                        let mut n = node.clone();
-                        print!("level={:?}", level);
-                        loop {
-                            let next = n.clone();
-                            print!(
-                                "offset={:?}, data={:?}\t",
-                                next.borrow().offset,
-                                next.borrow().data
-                            );
-                            match next.borrow().next[level] {
-                                Some(ref next) => {
-                                    n = next.clone();
-                                }
-                                _ => break,
-                            };
-                        }
-                    }
+    /// Renders every level from the top down, one line each, listing the
+    /// offset and data of each node on it.
+    fn level_path(&self) -> String {
+        let mut out = String::new();
+        if let Some(ref head) = self.head {
+            for level in (0..=self.max_level()).rev() {
+                out.push_str(&format!("level={:?}", level));
+                let mut n = head.clone();
+                loop {
+                    let next = n.clone();
+                    out.push_str(&format!(
+                        "\toffset={:?}, data={:?}",
+                        next.borrow().offset,
+                        next.borrow().data
+                    ));
+                    match next.borrow().next[level] {
+                        Some(ref next) => n = next.clone(),
+                        _ => break,
+                    };
                 }
-                None => {}
+                out.push('\n');
             }
         }
+        out
+    }
+
+    /// Dumps the node layout of every level, for attaching to bug reports.
+    /// Empty if the list is.
+    pub fn debug_structure(&self) -> String {
+        self.level_path()
+    }
+
     pub fn find(&self, offset: u64) -> Option<String> {
-        match self.head {
-            Some(ref head) => {
-                let mut start_level = self.max_level - 1; // should be max_level-1
-                let node = head.clone();
-                let mut result = None;
-                loop {
-                    if node.borrow().next[start_level].is_some() {
-                        break;
-                    }
-                    start_level -= 1;
+        self.find_with(offset, str::to_string)
+    }
+
+    /// Looks up `offset` like `find`, but applies `f` to the data while it is
+    /// borrowed instead of cloning it.
+    pub fn find_with<R>(&self, offset: u64, f: impl FnOnce(&str) -> R) -> Option<R> {
+        let mut n = self.head.clone()?;
+        for level in (0..=self.max_level).rev() {
+            loop {
+                let next = match n.borrow().next[level] {
+                    Some(ref next) if next.borrow().offset <= offset => next.clone(),
+                    _ => break,
+                };
+                n = next;
+            }
+        }
+        let node = n.borrow();
+        if node.offset == offset {
+            Some(f(&node.data))
+        } else {
+            None
+        }
+    }
+
+    /// Looks up every offset of `offsets`. Each search resumes from the node
+    /// the previous one stopped at, climbing no higher than that node's
+    /// height, so sorted offsets are found in one forward sweep instead of a
+    /// descent from the head per offset. An offset below the previous one
+    /// restarts from the head.
+    pub fn find_many(&self, offsets: &[u64]) -> Vec<Option<String>> {
+        let head = match self.head {
+            Some(ref head) => head.clone(),
+            None => return vec![None; offsets.len()],
+        };
+        let mut n = head.clone();
+        offsets
+            .iter()
+            .map(|&offset| {
+                if n.borrow().offset > offset {
+                    n = head.clone();
                 }
-                let mut n = node;
-                for level in (0..=start_level).rev() {
+                let height = n.borrow().next.len();
+                for level in (0..height).rev() {
                     loop {
-                        let next = n.clone();
-                        match next.borrow().next[level] {
-                            Some(ref tmp) => {
-                                if tmp.borrow().offset <= offset {
-                                    n = tmp.clone();
-                                } else {
-                                    break;
-                                }
-                            }
+                        let next = match n.borrow().next[level] {
+                            Some(ref next) if next.borrow().offset <= offset => next.clone(),
                             _ => break,
                         };
+                        n = next;
                     }
-                    if n.borrow().offset == offset {
-                        let tmp = n.borrow();
-                        result = Some(tmp.data.clone());
-                        break;
+                }
+                let node = n.borrow();
+                if node.offset == offset {
+                    Some(node.data.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Same lookup as `find`, but without cloning any `Rc` or touching the
+    /// `RefCell` borrow flags, so several threads may run it at once.
+    ///
+    /// # Safety
+    ///
+    /// No node of the list may be mutated for the duration of the call.
+    unsafe fn find_unguarded(&self, offset: u64) -> Option<String> {
+        let mut node = self.head.as_ref()?.try_borrow_unguarded().ok()?;
+        for level in (0..node.next.len()).rev() {
+            while let Some(next) = node.next[level].as_ref() {
+                let next = next.try_borrow_unguarded().ok()?;
+                if next.offset > offset {
+                    break;
+                }
+                node = next;
+            }
+        }
+        if node.offset == offset {
+            Some(node.data.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Returns, for every level, the last node whose offset is below
+    /// `offset`, along with its position in the list. `head` must be the
+    /// head node and lie below `offset`.
+    fn predecessors(&self, head: RealNode, offset: u64) -> (Vec<RealNode>, Vec<usize>) {
+        let mut update = vec![head.clone(); self.max_level + 1];
+        let mut ranks = vec![0; self.max_level + 1];
+        let mut x = head;
+        let mut rank = 0;
+        for level in (0..=self.max_level).rev() {
+            loop {
+                let (next, span) = match x.borrow().next[level] {
+                    Some(ref next) if next.borrow().offset < offset => {
+                        (next.clone(), x.borrow().span[level])
                     }
+                    _ => break,
+                };
+                x = next;
+                rank += span;
+            }
+            update[level] = x.clone();
+            ranks[level] = rank;
+        }
+        (update, ranks)
+    }
+
+    /// Returns the entries just below and just above `offset`. If `offset`
+    /// itself is stored, its entry is returned on both sides.
+    pub fn find_neighbors(&self, offset: u64) -> (Option<Entry>, Option<Entry>) {
+        fn entry(node: &RealNode) -> Entry {
+            let node = node.borrow();
+            (node.offset, node.data.clone())
+        }
+
+        let head = match self.head {
+            Some(ref head) => head.clone(),
+            None => return (None, None),
+        };
+        let head_offset = head.borrow().offset;
+        if head_offset >= offset {
+            let above = Some(entry(&head));
+            let below = if head_offset == offset { above.clone() } else { None };
+            return (below, above);
+        }
+        let (update, _) = self.predecessors(head, offset);
+        let floor = update[0].clone();
+        let next = floor.borrow().next[0].clone();
+        match next {
+            Some(ref next) if next.borrow().offset == offset => {
+                (Some(entry(next)), Some(entry(next)))
+            }
+            _ => (Some(entry(&floor)), next.as_ref().map(entry)),
+        }
+    }
+
+    /// Returns how many entries have an offset strictly below `offset`.
+    pub fn rank(&self, offset: u64) -> usize {
+        match self.head {
+            Some(ref head) if head.borrow().offset < offset => {
+                let (_, ranks) = self.predecessors(head.clone(), offset);
+                ranks[0] + 1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Inserts an entry at its ordered position. Returns false, leaving the
+    /// list unchanged, if the offset is already present.
+    pub fn insert(&mut self, offset: u64, data: String) -> bool {
+        let head = match self.head.clone() {
+            Some(head) => head,
+            None => {
+                self.append(offset, data);
+                return true;
+            }
+        };
+        let head_offset = head.borrow().offset;
+        if head_offset == offset {
+            return false;
+        }
+        let level = 1 + self.random_level();
+        if offset < head_offset {
+            self.index_add(offset, &data);
+            // the head keeps its full height: it takes the new entry and its
+            // old entry moves into a fresh node right behind it
+            let mut head_node = head.borrow_mut();
+            let old_data = std::mem::replace(&mut head_node.data, data);
+            head_node.offset = offset;
+            drop(head_node);
+            let update = vec![head; self.max_level + 1];
+            let ranks = vec![0; self.max_level + 1];
+            self.link_after(&update, &ranks, level, head_offset, old_data);
+            return true;
+        }
+        let (update, ranks) = self.predecessors(head, offset);
+        if let Some(ref next) = update[0].borrow().next[0] {
+            if next.borrow().offset == offset {
+                return false;
+            }
+        }
+        self.index_add(offset, &data);
+        self.link_after(&update, &ranks, level, offset, data);
+        true
+    }
+
+    /// Links a new node of height `level` right after `update[i]` on every
+    /// level `i` below `level`, where `ranks` holds the positions of the
+    /// `update` nodes as returned by `predecessors`.
+    fn link_after(
+        &mut self,
+        update: &[RealNode],
+        ranks: &[usize],
+        level: usize,
+        offset: u64,
+        data: String,
+    ) {
+        let node = Node::new(vec![None; level], offset, data);
+        let pos = ranks[0] + 1;
+        for (i, prev) in update.iter().enumerate() {
+            let mut prev = prev.borrow_mut();
+            if i < level {
+                let next = prev.next[i].replace(node.clone());
+                if next.is_none() {
+                    self.tails[i] = Some(node.clone());
                 }
-                result
+                let mut node = node.borrow_mut();
+                node.next[i] = next;
+                node.span[i] = prev.span[i] + 1 - (pos - ranks[i]);
+                prev.span[i] = pos - ranks[i];
+            } else {
+                prev.span[i] += 1;
+            }
+        }
+        self.length += 1;
+        self.grow_levels();
+    }
+
+    /// Returns the data stored at `offset`, inserting the result of `default`
+    /// first if there is none. `default` only runs on a miss.
+    pub fn get_or_insert(&mut self, offset: u64, default: impl FnOnce() -> String) -> String {
+        if let Some(data) = self.find(offset) {
+            return data;
+        }
+        let data = default();
+        self.insert(offset, data.clone());
+        data
+    }
+
+    /// Removes the entry with the given offset and returns its data.
+    pub fn remove(&mut self, offset: u64) -> Option<String> {
+        let head = self.head.clone()?;
+        if head.borrow().offset > offset {
+            return None;
+        }
+        if head.borrow().offset == offset {
+            let data = self.remove_head(head);
+            self.index_remove(offset, &data);
+            return Some(data);
+        }
+
+        let (update, _) = self.predecessors(head, offset);
+        let target = match update[0].borrow().next[0] {
+            Some(ref next) if next.borrow().offset == offset => next.clone(),
+            _ => return None,
+        };
+        let height = target.borrow().next.len();
+        for (level, prev) in update.into_iter().enumerate() {
+            if level >= height {
+                prev.borrow_mut().span[level] -= 1;
+                continue;
+            }
+            {
+                let target = target.borrow();
+                let mut prev = prev.borrow_mut();
+                prev.next[level] = target.next[level].clone();
+                prev.span[level] += target.span[level] - 1;
+            }
+            if matches!(self.tails[level], Some(ref tail) if Rc::ptr_eq(tail, &target)) {
+                self.tails[level] = Some(prev);
+            }
+        }
+        self.length -= 1;
+        let data = std::mem::take(&mut target.borrow_mut().data);
+        self.index_remove(offset, &data);
+        Some(data)
+    }
+
+    /// Removes the entry stored in the head node. The head has to keep its
+    /// full height, so the successor's entry is moved into it and the
+    /// successor node is unlinked instead.
+    fn remove_head(&mut self, head: RealNode) -> String {
+        self.length -= 1;
+        let succ = head.borrow().next[0].clone();
+        let succ = match succ {
+            Some(succ) => succ,
+            None => {
+                let data = std::mem::take(&mut head.borrow_mut().data);
+                self.head = None;
+                self.tails = vec![None; self.max_level + 1];
+                return data;
+            }
+        };
+        let mut head_node = head.borrow_mut();
+        let mut succ_node = succ.borrow_mut();
+        let data = std::mem::replace(&mut head_node.data, std::mem::take(&mut succ_node.data));
+        head_node.offset = succ_node.offset;
+        for level in 0..=self.max_level {
+            if level >= succ_node.next.len() {
+                head_node.span[level] -= 1;
+                continue;
+            }
+            head_node.next[level] = succ_node.next[level].take();
+            head_node.span[level] = succ_node.span[level];
+            if matches!(self.tails[level], Some(ref tail) if Rc::ptr_eq(tail, &succ)) {
+                self.tails[level] = Some(head.clone());
             }
-            None => None,
         }
+        data
+    }
+
+    /// Calls `f` on every (offset, data) pair in order of offset, lending the
+    /// data instead of cloning it.
+    pub fn for_each(&self, mut f: impl FnMut(u64, &str)) {
+        let mut link = self.head.clone();
+        while let Some(node) = link {
+            let node = node.borrow();
+            f(node.offset, &node.data);
+            link = node.next[0].clone();
+        }
+    }
+
+    /// Returns the (offset, data) pairs whose data starts with `prefix`, in
+    /// order of offset. This is a linear scan of the bottom level.
+    pub fn find_by_data_prefix(&self, prefix: &str) -> Vec<(u64, String)> {
+        let mut result = vec![];
+        let mut link = self.head.clone();
+        while let Some(node) = link {
+            let node = node.borrow();
+            if node.data.starts_with(prefix) {
+                result.push((node.offset, node.data.clone()));
+            }
+            link = node.next[0].clone();
+        }
+        result
+    }
+
+    /// Returns the offsets holding exactly `data`, in increasing order. This is
+    /// a map lookup if the list keeps a data index and a linear scan of the
+    /// bottom level otherwise.
+    pub fn offsets_for_data(&self, data: &str) -> Vec<u64> {
+        if let Some(ref index) = self.data_index {
+            return index.get(data).cloned().unwrap_or_default();
+        }
+        let mut result = vec![];
+        let mut link = self.head.clone();
+        while let Some(node) = link {
+            let node = node.borrow();
+            if node.data == data {
+                result.push(node.offset);
+            }
+            link = node.next[0].clone();
+        }
+        result
+    }
+
+    /// Approximates the heap and inline bytes held by the list: for every node
+    /// its `Rc` allocation (two counts plus the `RefCell<Node>`), its link and
+    /// span vectors, and its string data. The data index, if any, adds its
+    /// keys and offset vectors but not the hash table itself.
+    pub fn memory_estimate(&self) -> usize {
+        use std::mem::size_of;
+
+        let mut bytes = size_of::<SkipList>() + self.tails.capacity() * size_of::<Link>();
+        let mut link = self.head.clone();
+        while let Some(node) = link {
+            let node = node.borrow();
+            bytes += 2 * size_of::<usize>() + size_of::<RefCell<Node>>();
+            bytes += node.next.capacity() * size_of::<Link>();
+            bytes += node.span.capacity() * size_of::<usize>();
+            bytes += node.data.capacity();
+            link = node.next[0].clone();
+        }
+        if let Some(ref index) = self.data_index {
+            for (data, offsets) in index {
+                bytes += size_of::<String>() + data.capacity();
+                bytes += size_of::<Vec<u64>>() + offsets.capacity() * size_of::<u64>();
+            }
+        }
+        bytes
+    }
+
+    /// Returns the number of nodes linked at each level, from level 0 upward.
+    pub fn level_sizes(&self) -> Vec<usize> {
+        (0..=self.max_level)
+            .map(|level| {
+                let mut count = 0;
+                let mut link = self.head.clone();
+                while let Some(node) = link {
+                    count += 1;
+                    link = node.borrow().next[level].clone();
+                }
+                count
+            })
+            .collect()
+    }
+
+    /// Consumes the list and moves out its (offset, data) pairs in order.
+    fn into_entries(self) -> Vec<(u64, String)> {
+        let mut entries = Vec::with_capacity(self.length as usize);
+        let mut link = self.head;
+        while let Some(node) = link {
+            let mut node = node.borrow_mut();
+            entries.push((node.offset, std::mem::take(&mut node.data)));
+            link = node.next[0].clone();
+        }
+        entries
+    }
+
+    /// Combines two lists into a new one holding all of their entries in
+    /// order of offset. If both lists contain the same offset, only the entry
+    /// from `self` is kept. The result keeps a data index if either list did.
+    pub fn merge(self, other: SkipList) -> SkipList {
+        let mut merged = SkipList::new(1 + self.max_level.max(other.max_level));
+        if self.data_index.is_some() || other.data_index.is_some() {
+            merged.data_index = Some(HashMap::new());
+        }
+        merged.level_fn = self.level_fn.clone().or_else(|| other.level_fn.clone());
+        merged.auto_levels = self.auto_levels || other.auto_levels;
+        let mut left = self.into_entries().into_iter().peekable();
+        let mut right = other.into_entries().into_iter().peekable();
+        loop {
+            let l = left.peek().map(|entry| entry.0);
+            let r = right.peek().map(|entry| entry.0);
+            let entry = match (l, r) {
+                (Some(l), Some(r)) if l < r => left.next(),
+                (Some(l), Some(r)) if l > r => right.next(),
+                (Some(_), Some(_)) => {
+                    right.next();
+                    left.next()
+                }
+                (Some(_), None) => left.next(),
+                (None, _) => right.next(),
+            };
+            match entry {
+                Some((offset, data)) => {
+                    merged.append(offset, data);
+                }
+                None => break,
+            }
+        }
+        merged
+    }
+
+    /// Removes the entries with offsets at or above `offset` and returns them
+    /// as a new list with as many levels. Only the links crossing the cut are
+    /// changed; the first moved node is raised to full height as the new head.
+    pub fn split_off(&mut self, offset: u64) -> SkipList {
+        let mut other = SkipList::new(self.max_level + 1);
+        if self.data_index.is_some() {
+            other.data_index = Some(HashMap::new());
+        }
+        other.level_fn = self.level_fn.clone();
+        other.auto_levels = self.auto_levels;
+        let head = match self.head.clone() {
+            Some(head) => head,
+            None => return other,
+        };
+        if head.borrow().offset >= offset {
+            std::mem::swap(self, &mut other);
+            return other;
+        }
+        let (update, ranks) = self.predecessors(head, offset);
+        let first = match update[0].borrow().next[0].clone() {
+            Some(first) => first,
+            None => return other,
+        };
+        let kept = ranks[0] + 1;
+        {
+            let mut first_node = first.borrow_mut();
+            let height = first_node.next.len();
+            first_node.next.resize(self.max_level + 1, None);
+            first_node.span.resize(self.max_level + 1, 0);
+            for (level, prev) in update.iter().enumerate() {
+                let mut prev = prev.borrow_mut();
+                let next = prev.next[level].take();
+                if level >= height {
+                    // `next` sat at position ranks[level] + span, `first` at kept
+                    first_node.next[level] = next;
+                    first_node.span[level] = ranks[level] + prev.span[level] - kept;
+                }
+                prev.span[level] = kept - ranks[level];
+            }
+        }
+        for (level, prev) in update.into_iter().enumerate() {
+            let tail = self.tails[level].replace(prev);
+            other.tails[level] = if first.borrow().next[level].is_none() {
+                Some(first.clone())
+            } else {
+                tail
+            };
+        }
+        other.head = Some(first);
+        other.length = self.length - kept as u64;
+        self.length = kept as u64;
+
+        if other.data_index.is_some() {
+            let mut moved = vec![];
+            let mut link = other.head.clone();
+            while let Some(node) = link {
+                let node = node.borrow();
+                moved.push((node.offset, node.data.clone()));
+                link = node.next[0].clone();
+            }
+            for (offset, data) in moved {
+                self.index_remove(offset, &data);
+                other.index_add(offset, &data);
+            }
+        }
+        other
+    }
+}
+
+
+/// A `SkipList` behind an `RwLock`, shareable between threads. Lookups take
+/// the read lock and may run in parallel; `append` and `remove` take the
+/// write lock.
+pub struct ConcurrentSkipList {
+    inner: RwLock<SkipList>,
+}
+
+/// Shows only the length: the derived `Debug` of the nodes would borrow their
+/// `RefCell`s, which readers holding the read lock must not do.
+impl fmt::Debug for ConcurrentSkipList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConcurrentSkipList")
+            .field("length", &self.size())
+            .finish()
+    }
+}
+
+// SAFETY: the `Rc`s and `RefCell`s of the list are never handed out, so they
+// are only reached through `inner`. Everything that clones an `Rc` or borrows
+// a `RefCell` runs under the write lock, and readers go through
+// `find_unguarded`, which does neither.
+unsafe impl Send for ConcurrentSkipList {}
+unsafe impl Sync for ConcurrentSkipList {}
+
+impl ConcurrentSkipList {
+    pub fn new(level: usize) -> Self {
+        ConcurrentSkipList {
+            inner: RwLock::new(SkipList::new(level)),
+        }
+    }
+
+    pub fn find(&self, offset: u64) -> Option<String> {
+        let list = self.inner.read().unwrap();
+        // SAFETY: holding the read lock rules out writers
+        unsafe { list.find_unguarded(offset) }
+    }
+
+    pub fn append(&self, offset: u64, data: String) -> bool {
+        self.inner.write().unwrap().append(offset, data)
+    }
+
+    pub fn remove(&self, offset: u64) -> Option<String> {
+        self.inner.write().unwrap().remove(offset)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.inner.read().unwrap().length
     }
 }
 
@@ -152,4 +847,556 @@ mod test {
             assert_eq!(skl.find(i), Some(format!("data-{}", i)));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_level_sizes() {
+        let mut skl = SkipList::new(6);
+        for i in 0..1000 {
+            skl.append(i, format!("data-{}", i));
+        }
+        let sizes = skl.level_sizes();
+        assert_eq!(sizes.len(), 6);
+        assert_eq!(sizes[0], 1000);
+        for level in 1..sizes.len() {
+            assert!(sizes[level] < sizes[level - 1]);
+        }
+    }
+
+    #[test]
+    fn test_find_by_data_prefix() {
+        let mut skl = SkipList::new(4);
+        for i in 1..=100 {
+            skl.append(i, format!("data-{}", i));
+        }
+        let expected: Vec<_> = [1, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 100]
+            .iter()
+            .map(|&i| (i, format!("data-{}", i)))
+            .collect();
+        assert_eq!(skl.find_by_data_prefix("data-1"), expected);
+        assert_eq!(skl.find_by_data_prefix("data-").len(), 100);
+        assert!(skl.find_by_data_prefix("other").is_empty());
+    }
+
+    #[test]
+    fn test_for_each() {
+        let mut skl = SkipList::new(4);
+        for &i in [3, 1, 2].iter() {
+            skl.insert(i, format!("<{}>", i));
+        }
+        let mut joined = String::new();
+        let mut offsets = vec![];
+        skl.for_each(|offset, data| {
+            offsets.push(offset);
+            joined.push_str(data);
+        });
+        assert_eq!(offsets, vec![1, 2, 3]);
+        assert_eq!(joined, "<1><2><3>");
+        SkipList::new(2).for_each(|_, _| unreachable!());
+    }
+
+    #[test]
+    fn test_find_with() {
+        let mut skl = SkipList::new(4);
+        for i in 1..200 {
+            skl.append(i, "x".repeat(i as usize));
+        }
+        assert_eq!(skl.find_with(150, |data| data.len()), Some(150));
+        assert_eq!(skl.find_with(0, |data| data.len()), None);
+        assert_eq!(skl.find_with(1, |data| data == "x"), Some(true));
+        assert_eq!(skl.find_with(200, |_| unreachable!()), None::<()>);
+    }
+
+    #[test]
+    fn test_find_many() {
+        let mut skl = SkipList::new(5);
+        assert_eq!(skl.find_many(&[1, 2]), vec![None, None]);
+        for i in 0..500 {
+            skl.append(3 * i + 1, format!("data-{}", i));
+        }
+        let sorted: Vec<u64> = (0..1600).step_by(7).collect();
+        let expected: Vec<_> = sorted.iter().map(|&i| skl.find(i)).collect();
+        assert_eq!(skl.find_many(&sorted), expected);
+
+        let mixed = [1000, 4, 4, 1, 0, 1498, 1499, 7];
+        let expected: Vec<_> = mixed.iter().map(|&i| skl.find(i)).collect();
+        assert_eq!(skl.find_many(&mixed), expected);
+        assert!(skl.find_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_offsets_for_data() {
+        let mut plain = SkipList::new(4);
+        let mut indexed = SkipList::with_data_index(4);
+        for skl in [&mut plain, &mut indexed].iter_mut() {
+            for i in (0..60).rev() {
+                skl.insert(i * 10, format!("data-{}", i % 3));
+            }
+            assert!(!skl.append(30, "dup".to_string()));
+            skl.append(1000, "data-0".to_string());
+            skl.remove(0);
+            skl.remove(30);
+            skl.insert(5, "data-2".to_string());
+        }
+        for &skl in [&plain, &indexed].iter() {
+            let zeros = skl.offsets_for_data("data-0");
+            let expected: Vec<u64> = (2..20).map(|i| i * 30).chain(Some(1000)).collect();
+            assert_eq!(zeros, expected);
+            assert_eq!(skl.offsets_for_data("data-2")[..3], [5, 20, 50]);
+            assert!(skl.offsets_for_data("dup").is_empty());
+        }
+        let mut merged = indexed.merge(SkipList::new(3));
+        merged.compact();
+        assert_eq!(merged.offsets_for_data("data-1"), plain.offsets_for_data("data-1"));
+        merged.clear();
+        assert!(merged.offsets_for_data("data-0").is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut odd = SkipList::new(5);
+        let mut even = SkipList::new(4);
+        for i in 1..500 {
+            if i % 2 == 1 {
+                odd.append(i, format!("odd-{}", i));
+            } else {
+                even.append(i, format!("even-{}", i));
+            }
+        }
+        even.append(999, "even-999".to_string());
+        odd.append(999, "odd-999".to_string());
+
+        let merged = odd.merge(even);
+        assert_eq!(merged.size(), 500);
+        for i in 1..500 {
+            let prefix = if i % 2 == 1 { "odd" } else { "even" };
+            assert_eq!(merged.find(i), Some(format!("{}-{}", prefix, i)));
+        }
+        assert_eq!(merged.find(999), Some("odd-999".to_string()));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut skl = SkipList::new(5);
+        for i in 1..=100 {
+            skl.append(i, format!("data-{}", i));
+        }
+        assert_eq!(skl.remove(1), Some("data-1".to_string()));
+        assert_eq!(skl.remove(50), Some("data-50".to_string()));
+        assert_eq!(skl.remove(100), Some("data-100".to_string()));
+        assert_eq!(skl.remove(50), None);
+        assert_eq!(skl.remove(1000), None);
+        assert_eq!(skl.size(), 97);
+        skl.append(101, "data-101".to_string());
+        for i in 2..=101 {
+            let expected = match i {
+                50 | 100 => None,
+                _ => Some(format!("data-{}", i)),
+            };
+            assert_eq!(skl.find(i), expected);
+        }
+        for i in 2..=101 {
+            skl.remove(i);
+        }
+        assert_eq!(skl.size(), 0);
+        assert_eq!(skl.find_by_data_prefix(""), vec![]);
+        skl.append(7, "data-7".to_string());
+        assert_eq!(skl.level_sizes(), vec![1; 5]);
+    }
+
+    #[test]
+    fn test_concurrent_skip_list() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let skl = Arc::new(ConcurrentSkipList::new(6));
+        for i in 0..500 {
+            skl.append(i, format!("data-{}", i));
+        }
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let skl = skl.clone();
+                thread::spawn(move || {
+                    for _ in 0..5 {
+                        for i in 0..1000 {
+                            if let Some(data) = skl.find(i) {
+                                assert_eq!(data, format!("data-{}", i));
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        let writer = {
+            let skl = skl.clone();
+            thread::spawn(move || {
+                for i in 500..1000 {
+                    skl.append(i, format!("data-{}", i));
+                    if i % 2 == 0 {
+                        assert_eq!(skl.remove(i - 500), Some(format!("data-{}", i - 500)));
+                    }
+                }
+            })
+        };
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        writer.join().unwrap();
+
+        assert_eq!(skl.size(), 750);
+        for i in 0..1000 {
+            let expected = if i < 500 && i % 2 == 0 {
+                None
+            } else {
+                Some(format!("data-{}", i))
+            };
+            assert_eq!(skl.find(i), expected);
+        }
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut skl = SkipList::new(5);
+        let mut order: Vec<u64> = (0..200).map(|i| (i * 37) % 200 + 10).collect();
+        order.push(5);
+        order.push(1);
+        for &i in &order {
+            assert!(skl.insert(i, format!("data-{}", i)));
+        }
+        assert!(!skl.insert(1, "other".to_string()));
+        assert!(!skl.insert(100, "other".to_string()));
+        assert_eq!(skl.size(), 202);
+        for &i in &order {
+            assert_eq!(skl.find(i), Some(format!("data-{}", i)));
+        }
+        assert_eq!(skl.find(0), None);
+        assert_eq!(skl.find(7), None);
+        let offsets: Vec<u64> = skl.find_by_data_prefix("").iter().map(|e| e.0).collect();
+        order.sort();
+        assert_eq!(offsets, order);
+        skl.append(1000, "data-1000".to_string());
+        assert_eq!(skl.find(1000), Some("data-1000".to_string()));
+    }
+
+    #[test]
+    fn test_get_or_insert() {
+        let mut skl = SkipList::new(3);
+        let mut calls = 0;
+        for _ in 0..2 {
+            let data = skl.get_or_insert(42, || {
+                calls += 1;
+                "answer".to_string()
+            });
+            assert_eq!(data, "answer");
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(skl.get_or_insert(7, || "seven".to_string()), "seven");
+        assert_eq!(skl.get_or_insert(42, || unreachable!()), "answer");
+        assert_eq!(skl.size(), 2);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut skl = SkipList::new(5);
+        assert!(skl.is_empty());
+        for i in 1..=100_000 {
+            skl.append(i, format!("data-{}", i));
+        }
+        assert!(!skl.is_empty());
+        skl.clear();
+        assert!(skl.is_empty());
+        assert_eq!(skl.size(), 0);
+        assert_eq!(skl.find(1), None);
+        assert_eq!(skl.find(50), None);
+
+        for i in 1..=10 {
+            skl.append(i, format!("new-{}", i));
+        }
+        assert_eq!(skl.size(), 10);
+        assert_eq!(skl.find(5), Some("new-5".to_string()));
+        assert_eq!(skl.level_sizes()[0], 10);
+    }
+
+    #[test]
+    fn test_duplicate_offsets() {
+        let mut skl = SkipList::new(4);
+        assert!(skl.append(10, "first".to_string()));
+        assert!(!skl.append(10, "second".to_string()));
+        assert_eq!(skl.find(10), Some("first".to_string()));
+        assert!(skl.append(20, "twenty".to_string()));
+        assert!(!skl.append(20, "again".to_string()));
+        assert!(!skl.insert(10, "third".to_string()));
+
+        // appending below the last offset keeps the list ordered
+        assert!(skl.append(15, "fifteen".to_string()));
+        assert!(skl.append(5, "five".to_string()));
+        assert_eq!(skl.size(), 4);
+        let entries: Vec<_> = skl.find_by_data_prefix("").into_iter().map(|e| e.0).collect();
+        assert_eq!(entries, vec![5, 10, 15, 20]);
+        assert_eq!(skl.find(20), Some("twenty".to_string()));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut low = SkipList::new(4);
+        for i in 1..100 {
+            low.append(i, format!("data-{}", i));
+        }
+        let mut high = low.split_off(50);
+        assert_eq!((low.size(), high.size()), (49, 50));
+        for i in 0..110 {
+            let (expected_low, expected_high) = match i {
+                1..=49 => (Some(format!("data-{}", i)), None),
+                50..=99 => (None, Some(format!("data-{}", i))),
+                _ => (None, None),
+            };
+            assert_eq!(low.find(i), expected_low);
+            assert_eq!(high.find(i), expected_high);
+        }
+        assert!(high.level_sizes().iter().all(|&count| count >= 1));
+        assert_eq!(high.rank(75), 25);
+        assert_eq!(low.rank(1000), 49);
+
+        // both halves stay usable, with spans and tails intact
+        assert!(low.append(60, "data-60".to_string()));
+        assert!(high.insert(10, "data-10".to_string()));
+        assert_eq!(high.remove(99), Some("data-99".to_string()));
+        assert!(high.append(200, "data-200".to_string()));
+        assert_eq!(low.rank(61), 50);
+        assert_eq!(high.rank(200), 50);
+        assert_eq!(low.level_sizes()[0], 50);
+
+        assert_eq!(low.split_off(1000).size(), 0);
+        let all = low.split_off(0);
+        assert!(low.is_empty());
+        assert_eq!(all.size(), 50);
+        assert_eq!(all.find(60), Some("data-60".to_string()));
+
+        let mut indexed = SkipList::with_data_index(3);
+        for i in 0..20 {
+            indexed.append(i, format!("data-{}", i % 2));
+        }
+        let upper = indexed.split_off(15);
+        assert_eq!(indexed.offsets_for_data("data-1"), vec![1, 3, 5, 7, 9, 11, 13]);
+        assert_eq!(upper.offsets_for_data("data-1"), vec![15, 17, 19]);
+    }
+
+    #[test]
+    fn test_split_off_random() {
+        let mut rng = crate::rng::SmallRng::new(161);
+        for _ in 0..50 {
+            let mut skl = SkipList::new(5);
+            let mut naive = std::collections::BTreeSet::new();
+            for _ in 0..200 {
+                let offset = u64::from(rng.next_u32() % 500);
+                assert_eq!(skl.insert(offset, offset.to_string()), naive.insert(offset));
+            }
+            let cut = u64::from(rng.next_u32() % 520);
+            let mut upper = skl.split_off(cut);
+            let mut naive_upper = naive.split_off(&cut);
+            for _ in 0..100 {
+                let offset = u64::from(rng.next_u32() % 500);
+                let (skl, naive) = if offset < cut {
+                    (&mut skl, &mut naive)
+                } else {
+                    (&mut upper, &mut naive_upper)
+                };
+                match rng.next_u32() % 2 {
+                    0 => assert_eq!(skl.remove(offset).is_some(), naive.remove(&offset)),
+                    _ => assert_eq!(skl.insert(offset, offset.to_string()), naive.insert(offset)),
+                }
+                let probe = u64::from(rng.next_u32() % 510);
+                assert_eq!(skl.rank(probe), naive.range(..probe).count());
+            }
+            assert_eq!(skl.size(), naive.len() as u64);
+            assert_eq!(upper.size(), naive_upper.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut skl = SkipList::new(5);
+        for i in 1..100 {
+            skl.append(i, format!("data-{}", i));
+        }
+        assert_eq!(skl.rank(50), 49);
+        assert_eq!(skl.rank(0), 0);
+        assert_eq!(skl.rank(1), 0);
+        assert_eq!(skl.rank(1000), 99);
+
+        // keep the spans honest through inserts and removes anywhere
+        let mut rng = crate::rng::SmallRng::new(142);
+        let mut naive = std::collections::BTreeSet::new();
+        let mut skl = SkipList::new(6);
+        for _ in 0..3000 {
+            let offset = u64::from(rng.next_u32() % 300);
+            match rng.next_u32() % 3 {
+                0 => assert_eq!(skl.remove(offset).is_some(), naive.remove(&offset)),
+                _ => assert_eq!(skl.insert(offset, offset.to_string()), naive.insert(offset)),
+            }
+            let probe = u64::from(rng.next_u32() % 310);
+            assert_eq!(skl.rank(probe), naive.range(..probe).count());
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut skl = SkipList::new(6);
+        for i in 0..1000 {
+            skl.append(i, format!("data-{}", i));
+        }
+        let kept: Vec<u64> = (0..1000).filter(|i| i % 10 == 3).collect();
+        for i in 0..1000 {
+            if i % 10 != 3 {
+                assert!(skl.remove(i).is_some());
+            }
+        }
+        skl.compact();
+        assert_eq!(skl.size(), 100);
+        for i in 0..1000 {
+            let expected = if i % 10 == 3 { Some(format!("data-{}", i)) } else { None };
+            assert_eq!(skl.find(i), expected);
+        }
+        assert_eq!(skl.level_sizes(), vec![100, 50, 25, 13, 7, 4]);
+        assert_eq!(skl.rank(500), kept.iter().filter(|&&i| i < 500).count());
+        assert!(skl.append(1000, "data-1000".to_string()));
+        assert_eq!(skl.find(1000), Some("data-1000".to_string()));
+    }
+
+    #[test]
+    fn test_find_neighbors() {
+        let mut skl = SkipList::new(3);
+        assert_eq!(skl.find_neighbors(15), (None, None));
+        for &i in [10, 20, 30].iter() {
+            skl.append(i, format!("data-{}", i));
+        }
+        let entry = |i: u64| Some((i, format!("data-{}", i)));
+        assert_eq!(skl.find_neighbors(15), (entry(10), entry(20)));
+        assert_eq!(skl.find_neighbors(20), (entry(20), entry(20)));
+        assert_eq!(skl.find_neighbors(10), (entry(10), entry(10)));
+        assert_eq!(skl.find_neighbors(5), (None, entry(10)));
+        assert_eq!(skl.find_neighbors(30), (entry(30), entry(30)));
+        assert_eq!(skl.find_neighbors(31), (entry(30), None));
+    }
+
+    #[test]
+    fn test_memory_estimate() {
+        let empty = SkipList::new(4).memory_estimate();
+        let mut skl = SkipList::new(4);
+        let mut estimates = vec![];
+        for i in 1..=3000 {
+            skl.append(i, format!("{:08}", i));
+            if i % 1000 == 0 {
+                estimates.push(skl.memory_estimate() - empty);
+            }
+        }
+        let per_item = estimates[0] as f64 / 1000.0;
+        assert!(per_item > 8.0);
+        for (k, &bytes) in estimates.iter().enumerate() {
+            let ratio = bytes as f64 / (per_item * 1000.0 * (k + 1) as f64);
+            assert!(ratio > 0.9 && ratio < 1.1, "ratio {}", ratio);
+        }
+    }
+
+    #[test]
+    fn test_debug_structure() {
+        assert_eq!(SkipList::new(3).debug_structure(), "");
+
+        let mut skl = SkipList::new(3);
+        for i in 1..=20 {
+            skl.append(i * 10, format!("data-{}", i));
+        }
+        let dump = skl.debug_structure();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (line, level) in lines.iter().zip((0..3).rev()) {
+            assert!(line.starts_with(&format!("level={}\toffset=10, data=\"data-1\"", level)));
+        }
+        for i in 1..=20 {
+            assert!(lines[2].contains(&format!("offset={}, data=\"data-{}\"", i * 10, i)));
+        }
+    }
+
+    #[test]
+    fn test_max_offset() {
+        let mut skl = SkipList::new(4);
+        assert_eq!(skl.max_offset(), None);
+        for i in 1..=50 {
+            skl.append(i * 3, format!("data-{}", i));
+            assert_eq!(skl.max_offset(), Some(i * 3));
+        }
+        skl.insert(100, "data-x".to_string());
+        assert_eq!(skl.max_offset(), Some(150));
+        skl.insert(200, "data-y".to_string());
+        assert_eq!(skl.max_offset(), Some(200));
+        skl.remove(200);
+        assert_eq!(skl.max_offset(), Some(150));
+        skl.clear();
+        assert_eq!(skl.max_offset(), None);
+    }
+
+    #[test]
+    fn test_new_with_level_fn() {
+        // inserted in reverse, then appended, so both insert paths run
+        let fill = |mut skl: SkipList| {
+            for i in (0..300).rev() {
+                skl.insert(i * 2, format!("data-{}", i));
+            }
+            for i in 300..400 {
+                skl.append(i * 2, format!("data-{}", i));
+            }
+            skl
+        };
+        let flat = fill(SkipList::new_with_level_fn(5, || 0));
+        let tall = fill(SkipList::new_with_level_fn(5, || 4));
+        let capped = fill(SkipList::new_with_level_fn(5, || 99));
+        assert_eq!(flat.level_sizes(), vec![400, 1, 1, 1, 1]);
+        assert_eq!(tall.level_sizes(), vec![400; 5]);
+        assert_eq!(capped.level_sizes(), vec![400; 5]);
+
+        for skl in [flat, tall, capped].iter_mut() {
+            for i in 0..400 {
+                assert_eq!(skl.find(i * 2), Some(format!("data-{}", i)));
+                assert_eq!(skl.find(i * 2 + 1), None);
+            }
+            assert!(skl.remove(100).is_some());
+            assert_eq!(skl.find(100), None);
+            assert_eq!(skl.find(102), Some("data-51".to_string()));
+            skl.compact();
+            assert_eq!(skl.find(798), Some("data-399".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_with_auto_levels() {
+        let mut skl = SkipList::with_auto_levels(2);
+        let mut fixed = SkipList::new(2);
+        for i in 0..5000 {
+            skl.append(i * 2, format!("data-{}", i));
+            fixed.append(i * 2, format!("data-{}", i));
+        }
+        for i in (0..1000).rev() {
+            skl.insert(10_000 + i, format!("data-x{}", i));
+        }
+        assert_eq!(fixed.level_sizes().len(), 2);
+        let sizes = skl.level_sizes();
+        // 2^12 < 6000 <= 2^13, so levels 0..=13
+        assert_eq!(sizes.len(), 14);
+        assert_eq!(sizes[0], 6000);
+        assert!(sizes[4] > 1);
+
+        for i in 0..5000 {
+            assert_eq!(skl.find(i * 2), Some(format!("data-{}", i)));
+            assert_eq!(skl.find(i * 2 + 1), None);
+            assert_eq!(skl.rank(i * 2), i as usize);
+        }
+        for i in 0..1000 {
+            assert_eq!(skl.find(10_000 + i), Some(format!("data-x{}", i)));
+            assert_eq!(skl.rank(10_000 + i), 5000 + i as usize);
+        }
+
+        skl.compact();
+        assert_eq!(skl.level_sizes()[13], 1);
+        skl.append(20_000, String::new());
+        assert_eq!(skl.find(20_000), Some(String::new()));
+    }
+}
@@ -1,10 +1,34 @@
 //! Algorithms Cookbook in Rust.
+//!
+//! Without the default `std` feature, the crate builds against `core` and
+//! `alloc` alone, and only `graph::ring_buffer`, `range_query::seg_tree` and
+//! `rng` are included.
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
+#[macro_use]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod caching;
+#[cfg(feature = "std")]
 pub mod graph;
+#[cfg(feature = "std")]
 pub mod math;
+#[cfg(feature = "std")]
 pub mod order;
+#[cfg(feature = "std")]
 pub mod range_query;
 pub mod rng;
+#[cfg(feature = "std")]
 pub mod scanner;
+#[cfg(feature = "std")]
 pub mod string_proc;
+
+#[cfg(not(feature = "std"))]
+pub mod graph {
+    pub mod ring_buffer;
+}
+#[cfg(not(feature = "std"))]
+pub mod range_query {
+    pub mod seg_tree;
+}
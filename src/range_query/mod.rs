@@ -10,11 +10,26 @@ pub mod succinct_trie;
 pub use dynamic_arq::{ArqView, DynamicArq};
 pub use specs::ArqSpec;
 pub use static_arq::StaticArq;
-use radix_tree::RdxTree;
 
+/// Coordinate compression: returns the distinct values of `points` in
+/// increasing order, and a map from a coordinate to its index among them,
+/// for use as a dense `SegmentTree` index. A coordinate not in `points` maps
+/// to the index of the first larger value, so that it still works as the
+/// bound of a range.
+pub fn compress(points: &[i32]) -> (Vec<i32>, impl Fn(i32) -> usize) {
+    let mut sorted = points.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let keys = sorted.clone();
+    let index = move |x| match keys.binary_search(&x) {
+        Ok(i) | Err(i) => i,
+    };
+    (sorted, index)
+}
 
 #[cfg(test)]
 mod test {
+    use super::radix_tree::{Rdx, RdxSort, RdxTree};
     use super::specs::*;
     use super::*;
 
@@ -152,6 +167,423 @@ mod test {
         assert_eq!(tree.nnodes(), 11);
     }
 
+    #[test]
+    fn test_rdxtree_height() {
+        let mut tree: RdxTree<u8> = RdxTree::new();
+        assert_eq!(tree.height(), 0);
+        for x in 0..=255 {
+            tree.insert(x);
+        }
+        assert_eq!(tree.height(), <u8 as Rdx>::cfg_nrounds());
+        assert!(!tree.is_degenerate());
+
+        let mut sparse: RdxTree<u64> = RdxTree::new();
+        sparse.insert(0xdead_beef);
+        sparse.insert(0xdead_beee);
+        assert_eq!(sparse.height(), <u64 as Rdx>::cfg_nrounds());
+        assert!(sparse.is_degenerate());
+    }
+
+    #[test]
+    fn test_rdxtree_extend() {
+        let mut tree: RdxTree<i32> = RdxTree::new();
+        assert!(tree.insert(5));
+        assert!(!tree.insert(5));
+        assert_eq!(tree.extend(vec![3, -1, 5, 3, 8, -1, 0]), 4);
+        assert_eq!(tree.extend(vec![8, 0]), 0);
+        let values: Vec<i32> = tree.iter().cloned().collect();
+        assert_eq!(values, vec![-1, 0, 3, 5, 8]);
+    }
+
+    #[test]
+    fn test_rdxtree_contains() {
+        let mut tree: RdxTree<i16> = RdxTree::new();
+        assert!(!tree.contains(&0));
+        tree.extend(vec![-300, -1, 0, 42, 1000]);
+        assert!(tree.contains(&-1));
+        assert!(!tree.contains(&1));
+        assert!(tree.contains_all(&[42, -300, 1000]));
+        assert!(!tree.contains_all(&[42, -300, 999]));
+        assert!(tree.contains_all(&[]));
+        assert!(tree.contains_any(&[7, 8, 0]));
+        assert!(!tree.contains_any(&[7, 8, -2]));
+        assert!(!tree.contains_any(&[]));
+    }
+
+    #[test]
+    fn test_rdxtree_retain() {
+        let mut tree: RdxTree<u32> = RdxTree::new();
+        tree.extend(0..100);
+        let full = tree.nnodes();
+        tree.retain(|&x| x % 2 == 0);
+        let values: Vec<u32> = tree.iter().cloned().collect();
+        assert_eq!(values, (0..100).step_by(2).collect::<Vec<_>>());
+
+        tree.retain(|&x| x < 10);
+        assert_eq!(tree.iter().count(), 5);
+        assert!(tree.nnodes() < full);
+        tree.retain(|_| false);
+        assert_eq!(tree.nnodes(), 1);
+        assert_eq!(tree.height(), 0);
+        assert!(tree.insert(7));
+    }
+
+    #[test]
+    fn test_rdxtree_first_last_n() {
+        let mut tree: RdxTree<i32> = RdxTree::new();
+        tree.extend(0..1000);
+        assert_eq!(tree.first_n(5), vec![&0, &1, &2, &3, &4]);
+        assert_eq!(tree.last_n(3), vec![&999, &998, &997]);
+        assert_eq!(tree.first_n(2000).len(), 1000);
+        assert!(tree.last_n(0).is_empty());
+
+        tree.extend(vec![-7, -3]);
+        assert_eq!(tree.first_n(3), vec![&-7, &-3, &0]);
+        assert!(RdxTree::<u16>::new().last_n(4).is_empty());
+    }
+
+    #[test]
+    fn test_rdxtree_iter_with_path() {
+        let mut tree: RdxTree<u8> = RdxTree::new();
+        tree.extend(vec![0xa3, 0x07, 0x3a, 0xa4]);
+        let paths: Vec<(u8, Vec<usize>)> =
+            tree.iter_with_path().map(|(&x, path)| (x, path)).collect();
+        assert_eq!(
+            paths,
+            vec![
+                (0x07, vec![0x0, 0x7]),
+                (0x3a, vec![0x3, 0xa]),
+                (0xa3, vec![0xa, 0x3]),
+                (0xa4, vec![0xa, 0x4]),
+            ]
+        );
+        assert_eq!(RdxTree::<u8>::new().iter_with_path().count(), 0);
+    }
+
+    #[test]
+    fn test_rdxtree_into_sorted_vec() {
+        let mut rng = crate::rng::SmallRng::new(138);
+        let mut values: Vec<i64> = (0..10_000).map(|_| rng.next_u64() as i64).collect();
+        let mut tree: RdxTree<i64> = RdxTree::new();
+        tree.extend(values.iter().cloned());
+        values.sort();
+        assert_eq!(tree.into_sorted_vec(), values);
+        assert!(RdxTree::<u8>::new().into_sorted_vec().is_empty());
+    }
+
+    #[test]
+    fn test_rdxtree_to_dot() {
+        let mut tree: RdxTree<u16> = RdxTree::new();
+        tree.extend(vec![3, 300, 301, 4000]);
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph"));
+        assert_eq!(dot.matches("label=\"round").count(), tree.nnodes());
+        assert_eq!(dot.matches("shape=box").count(), 4);
+        assert_eq!(dot.matches("->").count(), tree.nnodes() - 1 + 4);
+        assert!(dot.contains("[label=\"4000\", shape=box]"));
+    }
+
+    #[test]
+    fn test_rdxsort_array() {
+        fn sort_generic<S: RdxSort>(s: &mut S) {
+            s.rdxsort();
+        }
+
+        let mut arr: [u32; 8] = [42, 7, 1024, 0, 7, 3, 99, 1];
+        sort_generic(&mut arr);
+        assert_eq!(arr, [0, 1, 3, 7, 7, 42, 99, 1024]);
+    }
+
+    #[test]
+    fn test_rdxsort_nonzero() {
+        use std::num::{NonZeroI32, NonZeroU32};
+
+        let mut rng = crate::rng::SmallRng::new(141);
+        let mut v: Vec<NonZeroU32> = (0..1000)
+            .filter_map(|_| NonZeroU32::new(rng.next_u32() >> (rng.next_u32() % 32)))
+            .collect();
+        let mut expected = v.clone();
+        expected.sort();
+        v.rdxsort();
+        assert_eq!(v, expected);
+
+        let mut v: Vec<NonZeroI32> = [5, -3, i32::MIN, 1, i32::MAX, -1]
+            .iter()
+            .map(|&x| NonZeroI32::new(x).unwrap())
+            .collect();
+        let mut expected = v.clone();
+        expected.sort();
+        v.rdxsort();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn test_is_rdx_sorted() {
+        assert!(radix_tree::is_rdx_sorted(&[-5i32, -1, 0, 0, 3, 1 << 20]));
+        assert!(!radix_tree::is_rdx_sorted(&[0u16, 2, 1]));
+        assert!(!radix_tree::is_rdx_sorted(&[0i8, -1]));
+        assert!(radix_tree::is_rdx_sorted::<u64>(&[]));
+    }
+
+    /// Reports its key the first time it's bucketed and the opposite key
+    /// afterwards, which no sort can satisfy.
+    #[derive(Clone)]
+    struct Flaky {
+        key: usize,
+        seen: std::cell::Cell<bool>,
+    }
+
+    impl Rdx for Flaky {
+        fn cfg_nbuckets() -> usize {
+            2
+        }
+        fn cfg_nrounds() -> usize {
+            1
+        }
+        fn get_bucket(&self, _round: usize) -> usize {
+            if self.seen.replace(true) {
+                1 - self.key
+            } else {
+                self.key
+            }
+        }
+        fn reverse(_round: usize, _bucket: usize) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "rdxsort: output is unsorted")]
+    fn test_rdxsort_catches_inconsistent_rdx() {
+        let mut v: Vec<Flaky> = [1, 0, 1, 0]
+            .iter()
+            .map(|&key| Flaky {
+                key,
+                seen: Default::default(),
+            })
+            .collect();
+        v.rdxsort();
+    }
+
+    #[test]
+    fn test_stable_partition_by() {
+        let mut pairs: Vec<(u32, usize)> = [7, 3, 9, 4, 1, 6, 0, 5, 2, 8, 3, 4]
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| (x, i))
+            .collect();
+        let bounds = radix_tree::stable_partition_by(&mut pairs, 3, |&(x, _)| x as usize % 3);
+
+        assert_eq!(bounds, vec![0, 5, 9, 12]);
+        for b in 0..3 {
+            let bucket = &pairs[bounds[b]..bounds[b + 1]];
+            assert!(bucket.iter().all(|&(x, _)| x as usize % 3 == b));
+            assert!(bucket.windows(2).all(|w| w[0].1 < w[1].1));
+        }
+    }
+
+    #[test]
+    fn test_rdxsort_msd() {
+        let prefix = "ab".repeat(2000);
+        let mut rng = crate::rng::SmallRng::new(7);
+        let mut words: Vec<String> = (0..500)
+            .map(|_| format!("{}{}", prefix, rng.next_u32() % 1000))
+            .collect();
+        words.push(String::new());
+        words.push(prefix.clone());
+        words.extend(["b", "a", "ba", "ab", "abc"].iter().map(|w| w.to_string()));
+        let mut expected = words.clone();
+        expected.sort();
+
+        radix_tree::rdxsort_msd(&mut words);
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn test_rdxsort_prefix() {
+        let mut rng = crate::rng::SmallRng::new(148);
+        let input: Vec<u32> = (0..2000).map(|_| rng.next_u32() & 0x0f00_ffff).collect();
+
+        let mut top = input.clone();
+        radix_tree::rdxsort_prefix(&mut top, 1);
+        let mut expected = input.clone();
+        expected.sort_by_key(|&x| x >> 24);
+        assert_eq!(top, expected);
+
+        let mut two = input.clone();
+        radix_tree::rdxsort_prefix(&mut two, 2);
+        expected.sort_by_key(|&x| x >> 16);
+        assert_eq!(two, expected);
+
+        let mut full = input;
+        radix_tree::rdxsort_prefix(&mut full, 4);
+        expected.sort();
+        assert_eq!(full, expected);
+    }
+
+    #[test]
+    fn test_rdx_argsort() {
+        let mut rng = crate::rng::SmallRng::new(152);
+        let v: Vec<i16> = (0..1000).map(|_| (rng.next_u32() % 200) as i16 - 100).collect();
+        let perm = radix_tree::rdx_argsort(&v);
+        let mut expected: Vec<usize> = (0..v.len()).collect();
+        expected.sort_by_key(|&i| v[i]);
+        assert_eq!(perm, expected);
+
+        let sorted: Vec<i16> = perm.iter().map(|&i| v[i]).collect();
+        let mut copy = v.clone();
+        copy.rdxsort();
+        assert_eq!(sorted, copy);
+        assert!(radix_tree::rdx_argsort::<u32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_rdx_select() {
+        let mut rng = crate::rng::SmallRng::new(163);
+        let mut v: Vec<u32> = (0..1001).map(|_| rng.next_u32() % 5000).collect();
+        let mut sorted = v.clone();
+        sorted.sort();
+        let mid = v.len() / 2;
+        radix_tree::rdx_select(&mut v, mid);
+        assert_eq!(v[mid], sorted[mid]);
+        assert!(v[..mid].iter().all(|&x| x <= v[mid]));
+        assert!(v[mid + 1..].iter().all(|&x| x >= v[mid]));
+
+        let mut w: Vec<i16> = (0..300).map(|_| rng.next_u32() as i16).collect();
+        let mut sorted = w.clone();
+        sorted.sort();
+        for &k in [0, 1, 150, 299].iter() {
+            radix_tree::rdx_select(&mut w, k);
+            assert_eq!(w[k], sorted[k]);
+        }
+        let mut one = [7u8];
+        radix_tree::rdx_select(&mut one, 0);
+        assert_eq!(one, [7]);
+    }
+
+    #[test]
+    fn test_radix_sorter_reuse() {
+        let mut rng = crate::rng::SmallRng::new(155);
+        let (arrays, max_len) = if cfg!(miri) { (20, 16) } else { (1000, 64) };
+        let mut sorter = radix_tree::RadixSorter::new();
+        for round in 0..arrays {
+            let len = rng.next_u32() as usize % max_len;
+            let mut v: Vec<i32> = (0..len).map(|_| rng.next_u32() as i32).collect();
+            let mut expected = v.clone();
+            expected.sort();
+            sorter.sort(&mut v);
+            assert_eq!(v, expected);
+            if round % 100 == 99 {
+                sorter.shrink_to_fit();
+            }
+        }
+    }
+
+    #[test]
+    fn test_radix_sorter_drops_clones() {
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Counted {
+            key: u32,
+            _owner: Rc<()>,
+        }
+
+        impl Rdx for Counted {
+            fn cfg_nbuckets() -> usize {
+                u32::cfg_nbuckets()
+            }
+            fn cfg_nrounds() -> usize {
+                u32::cfg_nrounds()
+            }
+            fn get_bucket(&self, round: usize) -> usize {
+                self.key.get_bucket(round)
+            }
+            fn reverse(round: usize, bucket: usize) -> bool {
+                u32::reverse(round, bucket)
+            }
+        }
+
+        let shared = Rc::new(());
+        let mut v: Vec<Counted> = (0..100)
+            .rev()
+            .map(|key| Counted {
+                key,
+                _owner: shared.clone(),
+            })
+            .collect();
+        let mut sorter = radix_tree::RadixSorter::new();
+        sorter.sort(&mut v);
+        assert!(v.iter().map(|c| c.key).eq(0..100));
+        // only the sorted elements and `shared` itself hold the Rc
+        assert_eq!(Rc::strong_count(&shared), 101);
+    }
+
+    #[test]
+    fn test_rdxsort_subslices() {
+        let mut rng = crate::rng::SmallRng::new(129);
+        let (n, rounds) = if cfg!(miri) { (64, 8) } else { (4096, 200) };
+        let mut arena: Vec<i32> = (0..n).map(|_| rng.next_u32() as i32).collect();
+        for round in 0..rounds {
+            let a = rng.next_u32() as usize % (n + 1);
+            let b = rng.next_u32() as usize % (n + 1);
+            let (l, r) = (a.min(b), a.max(b));
+            // make some buckets empty, including the trailing ones
+            if round % 2 == 0 {
+                for x in &mut arena[l..r] {
+                    *x &= 0x0f0f;
+                }
+            }
+            let mut expected = arena.clone();
+            expected[l..r].sort();
+
+            arena[l..r].rdxsort();
+            assert_eq!(arena, expected);
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Tagged {
+        key: i16,
+        tag: String,
+    }
+
+    impl Rdx for Tagged {
+        fn cfg_nbuckets() -> usize {
+            i16::cfg_nbuckets()
+        }
+        fn cfg_nrounds() -> usize {
+            i16::cfg_nrounds()
+        }
+        fn get_bucket(&self, round: usize) -> usize {
+            self.key.get_bucket(round)
+        }
+        fn reverse(round: usize, bucket: usize) -> bool {
+            i16::reverse(round, bucket)
+        }
+    }
+
+    #[test]
+    fn test_rdxsort_owned_values() {
+        let mut rng = crate::rng::SmallRng::new(1290);
+        let n = if cfg!(miri) { 50 } else { 2000 };
+        let mut v: Vec<Tagged> = (0..n)
+            .map(|i| Tagged {
+                key: (rng.next_u32() % 64) as i16 - 32,
+                tag: format!("tag-{}", i),
+            })
+            .collect();
+        let mut expected = v.clone();
+        expected[n / 4..].sort_by_key(|t| t.key);
+
+        v[n / 4..].rdxsort();
+        assert_eq!(v, expected);
+        v.rdxsort();
+        expected.sort_by_key(|t| t.key);
+        assert_eq!(v, expected);
+    }
+
     #[test]
     fn test_list() {
         
@@ -170,4 +602,271 @@ mod test {
         assert_eq!(first_neg, Some(3));
         assert_eq!(first_neg_zeros, None);
     }
+
+    #[test]
+    fn test_rdxsort_adaptive() {
+        let n = if cfg!(miri) { 1000 } else { 100_000 };
+        let sorted: Vec<u32> = (0..n).map(|i| i / 3).collect();
+        let mut v = sorted.clone();
+        v.rdxsort_adaptive();
+        assert_eq!(v, sorted);
+
+        // no pass ran, so not a single value was cloned and written back
+        let mut tagged: Vec<Tagged> = (0..100)
+            .map(|i| Tagged {
+                key: i - 50,
+                tag: format!("tag-{}", i),
+            })
+            .collect();
+        let before: Vec<*const u8> = tagged.iter().map(|t| t.tag.as_ptr()).collect();
+        tagged.rdxsort_adaptive();
+        let after: Vec<*const u8> = tagged.iter().map(|t| t.tag.as_ptr()).collect();
+        assert_eq!(before, after);
+
+        let mut rng = crate::rng::SmallRng::new(167);
+        let mut v: Vec<i32> = (0..1000).map(|_| rng.next_u32() as i32).collect();
+        let mut expected = v.clone();
+        expected.sort();
+        v.rdxsort_adaptive();
+        assert_eq!(v, expected);
+
+        // implementations written before rdxsort_adaptive fall back to rdxsort
+        struct Sorts(Vec<u8>);
+        impl RdxSort for Sorts {
+            fn rdxsort(&mut self) {
+                self.0.rdxsort();
+            }
+        }
+        let mut old = Sorts(vec![3, 1, 2]);
+        old.rdxsort_adaptive();
+        assert_eq!(old.0, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_rdxsort_duration() {
+        use std::time::Duration;
+
+        let mut rng = crate::rng::SmallRng::new(175);
+        let mut v: Vec<Duration> = (0..1000)
+            .map(|_| match rng.next_u32() % 3 {
+                0 => Duration::from_millis(u64::from(rng.next_u32() % 50)),
+                1 => Duration::new(rng.next_u64(), rng.next_u32() % 1_000_000_000),
+                _ => Duration::from_nanos(u64::from(rng.next_u32())),
+            })
+            .collect();
+        v.push(Duration::new(u64::MAX, 999_999_999));
+        v.push(Duration::from_secs(0));
+        let mut expected = v.clone();
+        expected.sort();
+        v.rdxsort();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn test_rdxtree_snapshot() {
+        use std::thread;
+
+        let mut tree = RdxTree::new();
+        tree.extend(0u32..1000);
+        let snap = tree.snapshot();
+        let reader = {
+            let snap = snap.clone();
+            thread::spawn(move || {
+                let seen: Vec<u32> = snap.iter().cloned().collect();
+                assert!(snap.contains(&500));
+                assert!(!snap.contains(&5000));
+                seen
+            })
+        };
+        tree.retain(|&x| x % 2 == 1);
+        tree.extend(5000u32..5100);
+
+        assert_eq!(reader.join().unwrap(), (0..1000).collect::<Vec<_>>());
+        assert!(snap.contains(&500));
+        assert!(!tree.contains(&500));
+        assert!(tree.contains(&5000));
+        assert_eq!(tree.iter().count(), 600);
+    }
+
+    #[test]
+    fn test_rdxtree_mutators_after_snapshot() {
+        let mut tree = RdxTree::new();
+        tree.extend(0u32..100);
+        let before = tree.snapshot();
+        assert!(tree.insert(1000));
+        assert!(!tree.insert(5));
+        tree.retain(|&x| x % 10 == 0);
+
+        let mut other = RdxTree::new();
+        other.extend(vec![7u32, 2000]);
+        let other_before = other.snapshot();
+        tree.merge(other);
+
+        let expected: Vec<u32> = vec![0, 7, 10, 20, 30, 40, 50, 60, 70, 80, 90, 1000, 2000];
+        let after = tree.snapshot();
+        assert_eq!(tree.into_sorted_vec(), expected);
+        assert_eq!(before.iter().cloned().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        assert_eq!(other_before.iter().cloned().collect::<Vec<_>>(), vec![7, 2000]);
+        assert_eq!(after.iter().cloned().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_rdxtree_without_clone() {
+        #[derive(Debug, PartialEq)]
+        struct Key(u16);
+
+        impl Rdx for Key {
+            fn cfg_nbuckets() -> usize {
+                u16::cfg_nbuckets()
+            }
+            fn cfg_nrounds() -> usize {
+                u16::cfg_nrounds()
+            }
+            fn get_bucket(&self, round: usize) -> usize {
+                self.0.get_bucket(round)
+            }
+            fn reverse(round: usize, bucket: usize) -> bool {
+                u16::reverse(round, bucket)
+            }
+        }
+
+        let mut tree = RdxTree::new();
+        assert_eq!(tree.extend((0..10).rev().map(Key)), 10);
+        assert!(!tree.insert(Key(3)));
+        tree.retain(|k| k.0 % 3 == 0);
+        let mut other = RdxTree::new();
+        other.insert(Key(4));
+        tree.merge(other);
+        assert_eq!(
+            tree.into_sorted_vec(),
+            vec![Key(0), Key(3), Key(4), Key(6), Key(9)]
+        );
+    }
+
+    #[test]
+    fn test_rdxsort_tiny_slices() {
+        fn check<T: Rdx + Clone + Ord + std::fmt::Debug>(a: T, b: T) {
+            let mut empty: Vec<T> = vec![];
+            empty.rdxsort();
+            assert!(empty.is_empty());
+
+            let mut single = vec![a.clone()];
+            single.rdxsort();
+            assert_eq!(single, vec![a.clone()]);
+
+            let mut sorted = [a.clone(), b.clone()];
+            sorted.sort();
+            let mut pair = [a.clone(), b.clone()];
+            pair.rdxsort();
+            assert_eq!(pair, sorted);
+            let mut pair = [b, a];
+            pair.rdxsort();
+            assert_eq!(pair, sorted);
+        }
+
+        check(200u8, 3u8);
+        check(7u8, 7u8);
+        check(u32::MAX, 0u32);
+        check(1u32 << 20, 1u32 << 4);
+        check(-5i32, 3i32);
+        check(i32::MIN, i32::MAX);
+        check(-1i32, -2i32);
+    }
+
+    #[test]
+    fn test_rdx_sorted() {
+        let input = [3, 1, 2];
+        assert_eq!(radix_tree::rdx_sorted(&input), vec![1, 2, 3]);
+        assert_eq!(input, [3, 1, 2]);
+        assert_eq!(radix_tree::rdx_sorted::<u8>(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_rdxsort_indices_by() {
+        struct Record {
+            age: u32,
+            name: &'static str,
+        }
+        let records = [
+            Record { age: 40, name: "ann" },
+            Record { age: 25, name: "bob" },
+            Record { age: 40, name: "cid" },
+            Record { age: 7, name: "dee" },
+        ];
+        let mut indices = [0, 1, 2, 3];
+        radix_tree::rdxsort_indices_by(&mut indices, &records, |r| r.age);
+        assert_eq!(indices, [3, 1, 0, 2]);
+        let names: Vec<_> = indices.iter().map(|&i| records[i].name).collect();
+        assert_eq!(names, ["dee", "bob", "ann", "cid"]);
+
+        let mut subset = [2, 3, 0];
+        radix_tree::rdxsort_indices_by(&mut subset, &records, |r| r.age);
+        assert_eq!(subset, [3, 2, 0]);
+    }
+
+    #[test]
+    fn test_rdxtree_merge() {
+        let mut a = RdxTree::new();
+        a.extend(0u32..10);
+        let mut b = RdxTree::new();
+        b.extend(20u32..25);
+        a.merge(b);
+        let expected: Vec<u32> = (0..10).chain(20..25).collect();
+        assert_eq!(a.iter().cloned().collect::<Vec<_>>(), expected);
+
+        let mut c = RdxTree::new();
+        c.extend(5u32..30);
+        a.merge(c);
+        assert_eq!(a.iter().cloned().collect::<Vec<_>>(), (0..30).collect::<Vec<_>>());
+
+        // a snapshot keeps the nodes of the merged tree alive
+        let mut d = RdxTree::new();
+        d.extend(vec![100u32, 50]);
+        let snap = d.snapshot();
+        a.merge(d);
+        assert!(a.contains(&100) && a.contains(&50));
+        assert_eq!(snap.iter().cloned().collect::<Vec<_>>(), vec![50, 100]);
+    }
+
+    #[test]
+    fn test_compress() {
+        let points = [100, 5, 100, 50];
+        let (values, index) = compress(&points);
+        assert_eq!(values, vec![5, 50, 100]);
+        let dense: Vec<usize> = points.iter().map(|&p| index(p)).collect();
+        assert_eq!(dense, vec![2, 0, 2, 1]);
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(index(v), i);
+        }
+        assert_eq!(index(-7), 0);
+        assert_eq!(index(60), 2);
+        assert_eq!(index(1000), 3);
+
+        let mut counts = seg_tree::SegmentTree::new_segment_tree(vec![0; values.len()]);
+        counts.build();
+        for &p in points.iter() {
+            let i = index(p);
+            counts.set(i, counts.get(i).unwrap() + 1).unwrap();
+        }
+        // how many points lie in [40, 100]
+        assert_eq!(counts.query(index(40), index(100)), Ok(3));
+    }
+
+    #[test]
+    fn test_rdx_bucket_histogram() {
+        let v: [u8; 6] = [0x00, 0x13, 0x23, 0xf3, 0x0f, 0x1f];
+        let mut expected = vec![0; 16];
+        expected[0x0] = 1;
+        expected[0x3] = 3;
+        expected[0xf] = 2;
+        assert_eq!(radix_tree::rdx_bucket_histogram(&v, 0), expected);
+
+        let mut expected = vec![0; 16];
+        expected[0x0] = 2;
+        expected[0x1] = 2;
+        expected[0x2] = 1;
+        expected[0xf] = 1;
+        assert_eq!(radix_tree::rdx_bucket_histogram(&v, 1), expected);
+        assert_eq!(radix_tree::rdx_bucket_histogram::<u8>(&[], 0), vec![0; 16]);
+    }
 }
@@ -1,8 +1,418 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::marker::PhantomData;
 
+#[derive(Default)]
 struct Node{
-    value:i32,
+    /// How many times the word ending at this node was inserted; nonzero if
+    /// a word ends here.
+    count:usize,
     next:HashMap<char, Rc<RefCell<Node>>>,
-}
\ No newline at end of file
+}
+
+/// Prefix tree over the characters of stored words.
+#[derive(Default)]
+pub struct Trie {
+    root: Rc<RefCell<Node>>,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a trie holding every word of `words`.
+    pub fn from_words(words: &[&str]) -> Trie {
+        let mut trie = Trie::new();
+        for word in words {
+            trie.insert(word);
+        }
+        trie
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        let mut node = self.root.clone();
+        for ch in word.chars() {
+            let next = node.borrow_mut().next.entry(ch).or_default().clone();
+            node = next;
+        }
+        node.borrow_mut().count += 1;
+    }
+
+    /// Returns true if `word` was inserted, as opposed to only being a prefix
+    /// of an inserted word.
+    pub fn search(&self, word: &str) -> bool {
+        let mut node = self.root.clone();
+        for ch in word.chars() {
+            let next = match node.borrow().next.get(&ch) {
+                Some(next) => next.clone(),
+                None => return false,
+            };
+            node = next;
+        }
+        let found = node.borrow().count != 0;
+        found
+    }
+
+    /// Returns how many times `word` was inserted and not deleted since.
+    pub fn word_frequency(&self, word: &str) -> usize {
+        let mut node = self.root.clone();
+        for ch in word.chars() {
+            let next = match node.borrow().next.get(&ch) {
+                Some(next) => next.clone(),
+                None => return 0,
+            };
+            node = next;
+        }
+        let count = node.borrow().count;
+        count
+    }
+
+    /// Undoes one insertion of `word`, returning false if it is not stored.
+    /// Nodes left without words below them are removed.
+    pub fn delete(&mut self, word: &str) -> bool {
+        fn remove(node: &mut Node, word: &[char]) -> bool {
+            match word.split_first() {
+                None if node.count == 0 => false,
+                None => {
+                    node.count -= 1;
+                    true
+                }
+                Some((ch, rest)) => {
+                    let next = match node.next.get(ch) {
+                        Some(next) => next.clone(),
+                        None => return false,
+                    };
+                    let removed = remove(&mut next.borrow_mut(), rest);
+                    let unused = next.borrow().count == 0 && next.borrow().next.is_empty();
+                    if unused {
+                        node.next.remove(ch);
+                    }
+                    removed
+                }
+            }
+        }
+
+        let word: Vec<char> = word.chars().collect();
+        remove(&mut self.root.borrow_mut(), &word)
+    }
+
+    /// Like `search`, but a '.' in `pattern` matches any single character.
+    pub fn search_pattern(&self, pattern: &str) -> bool {
+        fn matches(node: &Node, pattern: &[char]) -> bool {
+            match pattern.split_first() {
+                None => node.count != 0,
+                Some((&'.', rest)) => {
+                    node.next.values().any(|next| matches(&next.borrow(), rest))
+                }
+                Some((ch, rest)) => match node.next.get(ch) {
+                    Some(next) => matches(&next.borrow(), rest),
+                    None => false,
+                },
+            }
+        }
+
+        let pattern: Vec<char> = pattern.chars().collect();
+        matches(&self.root.borrow(), &pattern)
+    }
+
+    /// Returns all stored words in lexicographic order.
+    pub fn words(&self) -> Vec<String> {
+        self.iter().map(|(word, _)| word).collect()
+    }
+
+    /// Yields every stored word in lexicographic order as a `(word,
+    /// frequency)` pair, the frequency being what `word_frequency` returns.
+    /// The in-order DFS is lazy, keeping only the path to the current word.
+    pub fn iter(&self) -> TrieIter<'_> {
+        TrieIter {
+            start: Some(self.root.clone()),
+            stack: vec![],
+            prefix: String::new(),
+            trie: PhantomData,
+        }
+    }
+
+    /// Returns up to `k` of the words starting with `prefix`, with their
+    /// frequencies, most frequent first and lexicographically among equals.
+    pub fn top_completions(&self, prefix: &str, k: usize) -> Vec<(String, usize)> {
+        let mut node = self.root.clone();
+        for ch in prefix.chars() {
+            let next = match node.borrow().next.get(&ch) {
+                Some(next) => next.clone(),
+                None => return vec![],
+            };
+            node = next;
+        }
+        let mut words = vec![];
+        collect_words(&node.borrow(), &mut prefix.to_string(), &mut words);
+        // collected in lexicographic order, which the stable sort keeps for ties
+        words.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        words.truncate(k);
+        words
+    }
+
+    /// Returns the number of nodes, counting the root; words sharing a
+    /// prefix share the nodes along it.
+    pub fn node_count(&self) -> usize {
+        fn dfs(node: &Node) -> usize {
+            1 + node.next.values().map(|next| dfs(&next.borrow())).sum::<usize>()
+        }
+
+        dfs(&self.root.borrow())
+    }
+
+    /// Returns the number of distinct stored words.
+    pub fn word_count(&self) -> usize {
+        fn dfs(node: &Node) -> usize {
+            let here = (node.count != 0) as usize;
+            here + node.next.values().map(|next| dfs(&next.borrow())).sum::<usize>()
+        }
+
+        dfs(&self.root.borrow())
+    }
+
+    /// Returns the word with the most characters, the lexicographically
+    /// smallest among equally long ones, or None if the trie is empty.
+    pub fn longest_word(&self) -> Option<String> {
+        self.extreme_word(|len, best| len > best)
+    }
+
+    /// Returns the word with the fewest characters, the lexicographically
+    /// smallest among equally short ones, or None if the trie is empty.
+    pub fn shortest_word(&self) -> Option<String> {
+        self.extreme_word(|len, best| len < best)
+    }
+
+    /// Visits the words in lexicographic order with their depth, keeping the
+    /// first one whose depth is `better` than that of the word kept so far.
+    fn extreme_word(&self, better: fn(usize, usize) -> bool) -> Option<String> {
+        fn dfs(
+            node: &Node,
+            prefix: &mut String,
+            depth: usize,
+            better: fn(usize, usize) -> bool,
+            best: &mut Option<(usize, String)>,
+        ) {
+            let improves = match best {
+                Some((b, _)) => better(depth, *b),
+                None => true,
+            };
+            if node.count != 0 && improves {
+                *best = Some((depth, prefix.clone()));
+            }
+            let mut keys: Vec<&char> = node.next.keys().collect();
+            keys.sort();
+            for ch in keys {
+                prefix.push(*ch);
+                dfs(&node.next[ch].borrow(), prefix, depth + 1, better, best);
+                prefix.pop();
+            }
+        }
+
+        let mut best = None;
+        dfs(&self.root.borrow(), &mut String::new(), 0, better, &mut best);
+        best.map(|(_, word)| word)
+    }
+}
+
+/// In-order DFS over the words of a `Trie`, returned by `Trie::iter`.
+pub struct TrieIter<'a> {
+    /// The root, until the first call to `next` enters it.
+    start: Option<Rc<RefCell<Node>>>,
+    /// The nodes on the path to the last word yielded, each with the
+    /// characters of the children still to visit, largest first.
+    stack: Vec<(Rc<RefCell<Node>>, Vec<char>)>,
+    /// Spells the path to the top of `stack`.
+    prefix: String,
+    trie: PhantomData<&'a Trie>,
+}
+
+impl<'a> TrieIter<'a> {
+    /// Pushes `node` onto the path, returning its frequency.
+    fn enter(&mut self, node: Rc<RefCell<Node>>) -> usize {
+        let (count, mut keys) = {
+            let node = node.borrow();
+            (node.count, node.next.keys().cloned().collect::<Vec<char>>())
+        };
+        keys.sort_unstable_by(|a, b| b.cmp(a));
+        self.stack.push((node, keys));
+        count
+    }
+}
+
+impl<'a> Iterator for TrieIter<'a> {
+    type Item = (String, usize);
+
+    fn next(&mut self) -> Option<(String, usize)> {
+        if let Some(root) = self.start.take() {
+            let count = self.enter(root);
+            if count != 0 {
+                return Some((String::new(), count));
+            }
+        }
+        loop {
+            let (node, keys) = self.stack.last_mut()?;
+            match keys.pop() {
+                Some(ch) => {
+                    let child = node.borrow().next[&ch].clone();
+                    self.prefix.push(ch);
+                    let count = self.enter(child);
+                    if count != 0 {
+                        return Some((self.prefix.clone(), count));
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                    self.prefix.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Appends the words at or below `node` to `words` in lexicographic order,
+/// with their frequencies. `prefix` spells the path to `node`.
+fn collect_words(node: &Node, prefix: &mut String, words: &mut Vec<(String, usize)>) {
+    if node.count != 0 {
+        words.push((prefix.clone(), node.count));
+    }
+    let mut keys: Vec<&char> = node.next.keys().collect();
+    keys.sort();
+    for ch in keys {
+        prefix.push(*ch);
+        collect_words(&node.next[ch].borrow(), prefix, words);
+        prefix.pop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_words_round_trip() {
+        let dict = ["banana", "ban", "benefit", "", "banapple", "été", "ban"];
+        let trie = Trie::from_words(&dict);
+        let words = trie.words();
+        assert_eq!(words, vec!["", "ban", "banana", "banapple", "benefit", "été"]);
+
+        let word_refs: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        let rebuilt = Trie::from_words(&word_refs);
+        for word in dict.iter() {
+            assert!(trie.search(word));
+            assert!(rebuilt.search(word));
+        }
+        for word in ["b", "bana", "bananas", "x"].iter() {
+            assert_eq!(rebuilt.search(word), trie.search(word));
+            assert!(!rebuilt.search(word));
+        }
+        assert_eq!(rebuilt.words(), words);
+    }
+
+    #[test]
+    fn test_search_pattern() {
+        let trie = Trie::from_words(&["bad", "dad", "mad", "bade"]);
+        assert!(trie.search_pattern(".ad"));
+        assert!(trie.search_pattern("b.."));
+        assert!(trie.search_pattern("b..e"));
+        assert!(trie.search_pattern("..."));
+        assert!(!trie.search_pattern("pad"));
+        assert!(!trie.search_pattern("ba"));
+        assert!(!trie.search_pattern(".."));
+        assert!(!trie.search_pattern("....."));
+    }
+
+    #[test]
+    fn test_longest_shortest_word() {
+        let trie = Trie::from_words(&["a", "abc", "ab"]);
+        assert_eq!(trie.longest_word(), Some("abc".to_string()));
+        assert_eq!(trie.shortest_word(), Some("a".to_string()));
+
+        let trie = Trie::from_words(&["dog", "cat", "été", "zebra", "horse"]);
+        assert_eq!(trie.longest_word(), Some("horse".to_string()));
+        assert_eq!(trie.shortest_word(), Some("cat".to_string()));
+        assert_eq!(Trie::new().longest_word(), None);
+        assert_eq!(Trie::from_words(&[""]).shortest_word(), Some(String::new()));
+    }
+
+    #[test]
+    fn test_node_word_count() {
+        let words = ["cat", "car", "card"];
+        let trie = Trie::from_words(&words);
+        assert_eq!(trie.word_count(), 3);
+        // root, c, a, t, r, d
+        assert_eq!(trie.node_count(), 6);
+        let total: usize = words.iter().map(|w| w.len()).sum();
+        assert!(trie.node_count() < total);
+
+        assert_eq!(Trie::new().node_count(), 1);
+        assert_eq!(Trie::new().word_count(), 0);
+        assert_eq!(Trie::from_words(&["", "a", "a"]).word_count(), 2);
+    }
+
+    #[test]
+    fn test_word_frequency_delete() {
+        let mut trie = Trie::from_words(&["hi", "hi", "him"]);
+        trie.insert("hi");
+        assert_eq!(trie.word_frequency("hi"), 3);
+        assert_eq!(trie.word_frequency("h"), 0);
+        assert_eq!(trie.word_frequency("hit"), 0);
+        assert!(trie.delete("hi"));
+        assert_eq!(trie.word_frequency("hi"), 2);
+        assert_eq!(trie.word_count(), 2);
+
+        assert!(!trie.delete("h"));
+        assert!(trie.delete("him"));
+        assert!(!trie.delete("him"));
+        assert_eq!(trie.words(), vec!["hi"]);
+        assert_eq!(trie.node_count(), 3);
+        assert!(trie.delete("hi") && trie.delete("hi"));
+        assert!(!trie.search("hi"));
+        assert_eq!(trie.node_count(), 1);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut trie = Trie::from_words(&["pear", "apple", "pea", "apple", "zoo"]);
+        trie.insert("pea");
+        trie.insert("apple");
+        let pairs: Vec<_> = trie.iter().collect();
+        let expected = [("apple", 3), ("pea", 2), ("pear", 1), ("zoo", 1)];
+        let expected: Vec<_> = expected.iter().map(|&(w, n)| (w.to_string(), n)).collect();
+        assert_eq!(pairs, expected);
+        assert_eq!(Trie::new().iter().next(), None);
+
+        let mut it = trie.iter();
+        assert_eq!(it.next(), Some(("apple".to_string(), 3)));
+        assert_eq!(it.next(), Some(("pea".to_string(), 2)));
+
+        let mut with_empty = Trie::from_words(&["", "a", "ab"]);
+        with_empty.insert("");
+        let words: Vec<_> = with_empty.iter().collect();
+        assert_eq!(words, vec![("".to_string(), 2), ("a".to_string(), 1), ("ab".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_top_completions() {
+        let mut trie = Trie::new();
+        let counts = [("apple", 3), ("ant", 5), ("and", 1), ("art", 3), ("bee", 9)];
+        for &(word, times) in counts.iter() {
+            for _ in 0..times {
+                trie.insert(word);
+            }
+        }
+        let top = |prefix, k| -> Vec<(String, usize)> { trie.top_completions(prefix, k) };
+        let pairs = |v: &[(&str, usize)]| -> Vec<(String, usize)> {
+            v.iter().map(|&(w, n)| (w.to_string(), n)).collect()
+        };
+        assert_eq!(top("a", 2), pairs(&[("ant", 5), ("apple", 3)]));
+        assert_eq!(top("a", 3), pairs(&[("ant", 5), ("apple", 3), ("art", 3)]));
+        assert_eq!(top("an", 10), pairs(&[("ant", 5), ("and", 1)]));
+        assert_eq!(top("", 1), pairs(&[("bee", 9)]));
+        assert_eq!(top("ant", 1), pairs(&[("ant", 5)]));
+        assert_eq!(top("c", 3), vec![]);
+        assert_eq!(top("a", 0), vec![]);
+    }
+}
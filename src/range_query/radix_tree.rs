@@ -1,8 +1,11 @@
+use std::iter;
 use std::slice;
 use std::fmt;
 use std::cmp;
-use core::ptr;
 use std::mem;
+use std::num;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 pub trait Rdx {
     /// Set the number of buckets used by the generic implementation
@@ -182,18 +185,79 @@ impl Rdx for bool {
     }
 }
 
+/// Sorts by `as_nanos()`. A `Duration` spans fewer than 96 bits of
+/// nanoseconds, so 24 rounds of 4 bits cover it.
+impl Rdx for Duration {
+    #[inline]
+    fn cfg_nbuckets() -> usize {
+        16
+    }
+
+    #[inline]
+    fn cfg_nrounds() -> usize {
+        24
+    }
+
+    #[inline]
+    fn get_bucket(&self, round: usize) -> usize {
+        let shift = round << 2;
+        ((self.as_nanos() >> shift) & 15u128) as usize
+    }
+
+    #[inline]
+    fn reverse(_round: usize, _bucket: usize) -> bool {
+        false
+    }
+}
+
 impl_rdxsort!(i8, u8, i8::min_value(), 0i8);
 impl_rdxsort!(i16, u16, i16::min_value(), 0i16);
 impl_rdxsort!(i32, u32, i32::min_value(), 0i32);
 impl_rdxsort!(i64, u64, i64::min_value(), 0i64);
 
+/// Sorts `NonZero*` integers exactly like their underlying type.
+macro_rules! impl_rdx_nonzero {
+    ($($t:ty => $base:ty),*) => {
+        $(impl Rdx for $t {
+            #[inline]
+            fn cfg_nbuckets() -> usize {
+                <$base as Rdx>::cfg_nbuckets()
+            }
 
+            #[inline]
+            fn cfg_nrounds() -> usize {
+                <$base as Rdx>::cfg_nrounds()
+            }
+
+            #[inline]
+            fn get_bucket(&self, round: usize) -> usize {
+                self.get().get_bucket(round)
+            }
+
+            #[inline]
+            fn reverse(round: usize, bucket: usize) -> bool {
+                <$base as Rdx>::reverse(round, bucket)
+            }
+        })*
+    }
+}
+
+impl_rdx_nonzero!(num::NonZeroU8 => u8, num::NonZeroU16 => u16,
+                  num::NonZeroU32 => u32, num::NonZeroU64 => u64,
+                  num::NonZeroI8 => i8, num::NonZeroI16 => i16,
+                  num::NonZeroI32 => i32, num::NonZeroI64 => i64);
+
+
+/// Inner nodes sit behind an `Arc`, so snapshots can share them. A mutation
+/// copies the path to the nodes it changes if a snapshot still holds them.
+#[derive(Clone)]
 enum Node<T: Rdx> {
-    Inner(NodeInner<T>),
+    Inner(Arc<NodeInner<T>>),
     Child(T),
     Free,
 }
 
+#[derive(Clone)]
 struct NodeInner<T: Rdx> {
     round: usize,
     children: Vec<Node<T>>,
@@ -211,43 +275,62 @@ impl<T: Rdx> NodeInner<T> {
         }
     }
 
-    fn insert(&mut self, x: T) {
+    /// Inserts `x`, returning false if it replaced an equal leaf.
+    fn insert(&mut self, x: T, copy: Option<CopyInner<T>>) -> bool {
         let bucket = x.get_bucket(self.round - 1);
 
         if self.round > 1 {
             let clen = self.children.len();
-            let replace = match self.children[bucket] {
+            match self.children[bucket] {
                 Node::Free => {
                     let mut inner = NodeInner::new(self.round - 1, clen);
-                    inner.insert(x);
-                    Some(inner)
-                }
-                Node::Inner(ref mut inner) => {
-                    inner.insert(x);
-                    None
+                    inner.insert(x, copy);
+                    self.children[bucket] = Node::Inner(Arc::new(inner));
+                    true
                 }
+                Node::Inner(ref mut inner) => unshare(inner, copy).insert(x, copy),
                 Node::Child(_) => unreachable!(),
-            };
-
-            if let Some(inner) = replace {
-                self.children[bucket] = Node::Inner(inner);
             }
         } else {
-            let alloc = match self.children[bucket] {
-                Node::Free => true,
-                Node::Child(_) => false,
+            match self.children[bucket] {
+                Node::Free => {
+                    self.children[bucket] = Node::Child(x);
+                    true
+                }
+                Node::Child(ref mut y) => {
+                    *y = x; // XXX: is that a good idea?
+                    false
+                }
                 Node::Inner(_) => unreachable!(),
-            };
+            }
+        }
+    }
+
+    fn contains(&self, x: &T) -> bool {
+        match self.children[x.get_bucket(self.round - 1)] {
+            Node::Inner(ref inner) => inner.contains(x),
+            Node::Child(_) => true,
+            Node::Free => false,
+        }
+    }
 
-            if alloc {
-                self.children[bucket] = Node::Child(x);
+    /// Drops the leaves failing `pred` and prunes inner nodes left empty.
+    /// Returns false if this node ends up empty itself.
+    fn retain<F: Fn(&T) -> bool>(&mut self, pred: &F, copy: Option<CopyInner<T>>) -> bool {
+        let mut occupied = false;
+        for c in self.children.iter_mut() {
+            let keep = match *c {
+                Node::Inner(ref mut inner) => unshare(inner, copy).retain(pred, copy),
+                Node::Child(ref x) => pred(x),
+                Node::Free => false,
+            };
+            if keep {
+                occupied = true;
             } else {
-                match self.children[bucket] {
-                    Node::Child(ref mut y) => *y = x, // XXX: is that a good idea?
-                    _ => unreachable!(),
-                }
+                *c = Node::Free;
             }
         }
+        occupied
     }
 
     fn nnodes(&self) -> usize {
@@ -262,10 +345,63 @@ impl<T: Rdx> NodeInner<T> {
         }
         result
     }
+
+    fn height(&self) -> usize {
+        let mut result = 0;
+        for c in self.children.iter() {
+            match c {
+                Node::Inner(inner) => result = cmp::max(result, 1 + inner.height()),
+                Node::Child(_) => result = cmp::max(result, 1),
+                Node::Free => {}
+            }
+        }
+        result
+    }
+
+    /// Returns the number of inner nodes in this subtree, and how many of
+    /// those have exactly one occupied child.
+    fn count_single_child(&self) -> (usize, usize) {
+        let mut total = 1;
+        let mut single = 0;
+        let mut occupied = 0;
+        for c in self.children.iter() {
+            match c {
+                Node::Inner(inner) => {
+                    let (t, s) = inner.count_single_child();
+                    total += t;
+                    single += s;
+                    occupied += 1;
+                }
+                Node::Child(_) => occupied += 1,
+                Node::Free => {}
+            }
+        }
+        if occupied == 1 {
+            single += 1;
+        }
+        (total, single)
+    }
+}
+
+/// Copies an inner node that a snapshot still holds.
+type CopyInner<T> = fn(&NodeInner<T>) -> NodeInner<T>;
+
+/// Returns the node behind `inner` for mutation, first replacing it with a
+/// copy if a snapshot shares it.
+fn unshare<T: Rdx>(inner: &mut Arc<NodeInner<T>>, copy: Option<CopyInner<T>>) -> &mut NodeInner<T> {
+    if Arc::get_mut(inner).is_none() {
+        let copy = copy.expect("nodes are only shared by snapshots");
+        *inner = Arc::new(copy(inner));
+    }
+    Arc::get_mut(inner).unwrap()
 }
 
 pub struct RdxTree<T: Rdx> {
     root: Node<T>,
+    /// Set by `snapshot`, the only way nodes become shared, so that only
+    /// trees which were snapshotted need `T: Clone`. Behind a `Mutex` so that
+    /// `snapshot` can take `&self`; mutators reach it through `get_mut`.
+    copy_inner: Mutex<Option<CopyInner<T>>>,
 }
 
 impl<T: Rdx> RdxTree<T> {
@@ -273,21 +409,76 @@ impl<T: Rdx> RdxTree<T> {
         let rounds = <T as Rdx>::cfg_nrounds();
         let buckets = <T as Rdx>::cfg_nbuckets();
         RdxTree {
-            root: Node::Inner(NodeInner::<T>::new(rounds, buckets)),
+            root: Node::Inner(Arc::new(NodeInner::<T>::new(rounds, buckets))),
+            copy_inner: Mutex::new(None),
         }
     }
 
-    pub fn insert(&mut self, x: T) {
+    /// Returns how to copy the nodes shared with a snapshot, if one was taken.
+    fn copy_inner(&mut self) -> Option<CopyInner<T>> {
+        *self.copy_inner.get_mut().unwrap()
+    }
+
+    /// Inserts `x`, returning true if it was not in the tree yet. An equal
+    /// value already in the tree is overwritten.
+    pub fn insert(&mut self, x: T) -> bool {
+        let copy = self.copy_inner();
         match self.root {
-            Node::Inner(ref mut inner) => {
-                inner.insert(x);
-            }
+            Node::Inner(ref mut inner) => unshare(inner, copy).insert(x, copy),
             _ => {
                 unreachable!();
             }
         }
     }
 
+    /// Inserts every value of `iter`, returning how many of them were new.
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> usize {
+        let mut count = 0;
+        for x in iter {
+            if self.insert(x) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Moves every value of `other` into `self`. As with `insert`, a value
+    /// equal to one already stored replaces it.
+    pub fn merge(&mut self, other: RdxTree<T>) {
+        self.extend(other.into_sorted_vec());
+    }
+
+    /// Returns true if a value with the same buckets as `x` in every round
+    /// is in the tree.
+    pub fn contains(&self, x: &T) -> bool {
+        match self.root {
+            Node::Inner(ref inner) => inner.contains(x),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns true if every value of `xs` is in the tree.
+    pub fn contains_all(&self, xs: &[T]) -> bool {
+        xs.iter().all(|x| self.contains(x))
+    }
+
+    /// Returns true if some value of `xs` is in the tree.
+    pub fn contains_any(&self, xs: &[T]) -> bool {
+        xs.iter().any(|x| self.contains(x))
+    }
+
+    /// Removes every value for which `pred` returns false, pruning inner
+    /// nodes that become empty.
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, pred: F) {
+        let copy = self.copy_inner();
+        match self.root {
+            Node::Inner(ref mut inner) => {
+                unshare(inner, copy).retain(&pred, copy);
+            }
+            _ => unreachable!(),
+        }
+    }
+
     pub fn iter<'a>(&'a self) -> RdxTreeIter<'a, T> {
         let mut iters = Vec::new();
         match self.root {
@@ -299,6 +490,102 @@ impl<T: Rdx> RdxTree<T> {
         RdxTreeIter { iters: iters }
     }
 
+    /// Returns a read-only view of the current contents in O(1). The view
+    /// shares the nodes with the tree and can be sent to other threads;
+    /// later changes to the tree copy the nodes they touch instead.
+    ///
+    /// Only this method needs `T: Clone`: it records how to copy a node for
+    /// the mutators, which rely on snapshots being the only way nodes become
+    /// shared and panic on a shared node otherwise.
+    pub fn snapshot(&self) -> Arc<RdxTreeView<T>>
+    where
+        T: Clone,
+    {
+        *self.copy_inner.lock().unwrap() = Some(<NodeInner<T> as Clone>::clone);
+        Arc::new(RdxTreeView {
+            tree: RdxTree {
+                root: self.root.clone_inner(),
+                copy_inner: Mutex::new(None),
+            },
+        })
+    }
+
+    /// Like `iter`, but also yields the buckets taken from the root down to
+    /// each value, one per round starting with the last.
+    pub fn iter_with_path(&self) -> RdxTreePathIter<'_, T> {
+        let iters = match self.root {
+            Node::Inner(ref inner) => vec![inner.children.iter().enumerate()],
+            _ => unreachable!(),
+        };
+        RdxTreePathIter {
+            iters,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the `n` smallest values in order. The traversal stops once `n`
+    /// values are found.
+    pub fn first_n(&self, n: usize) -> Vec<&T> {
+        self.iter().take(n).collect()
+    }
+
+    /// Returns the `n` largest values, largest first. The traversal walks the
+    /// children from the back and stops once `n` values are found.
+    pub fn last_n(&self, n: usize) -> Vec<&T> {
+        let mut result = Vec::new();
+        let mut iters = match self.root {
+            Node::Inner(ref inner) => vec![inner.children.iter()],
+            _ => unreachable!(),
+        };
+        while result.len() < n {
+            let it = match iters.last_mut() {
+                Some(it) => it,
+                None => break,
+            };
+            match it.next_back() {
+                Some(Node::Free) => {}
+                Some(Node::Child(x)) => result.push(x),
+                Some(Node::Inner(inner)) => iters.push(inner.children.iter()),
+                None => {
+                    iters.pop();
+                }
+            }
+        }
+        result
+    }
+
+    /// Consumes the tree and moves its values out in the order of `iter()`.
+    /// Each inner node is freed as soon as its children are visited; nodes
+    /// still shared with a snapshot are copied instead.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        let copy = self.copy_inner.into_inner().unwrap();
+        let children = |inner: Arc<NodeInner<T>>| {
+            Arc::try_unwrap(inner)
+                .unwrap_or_else(|shared| {
+                    copy.expect("nodes are only shared by snapshots")(&shared)
+                })
+                .children
+                .into_iter()
+        };
+
+        let mut result = Vec::new();
+        let mut iters = match self.root {
+            Node::Inner(inner) => vec![children(inner)],
+            _ => unreachable!(),
+        };
+        while let Some(it) = iters.last_mut() {
+            match it.next() {
+                Some(Node::Free) => {}
+                Some(Node::Child(x)) => result.push(x),
+                Some(Node::Inner(inner)) => iters.push(children(inner)),
+                None => {
+                    iters.pop();
+                }
+            }
+        }
+        result
+    }
+
     pub fn nnodes(&self) -> usize {
         match self.root {
             Node::Inner(ref inner) => inner.nnodes(),
@@ -307,6 +594,64 @@ impl<T: Rdx> RdxTree<T> {
             }
         }
     }
+
+    /// Returns the number of inner-node levels on the longest root-to-leaf
+    /// path, which is `cfg_nrounds()` for any non-empty tree and 0 otherwise.
+    pub fn height(&self) -> usize {
+        match self.root {
+            Node::Inner(ref inner) => inner.height(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Renders the tree in Graphviz DOT format. Inner nodes are labeled with
+    /// their round, leaves with their value, and edges with the bucket.
+    pub fn to_dot(&self) -> String
+    where
+        T: fmt::Display,
+    {
+        let mut out = String::from("digraph RdxTree {\n");
+        dot_node(&self.root, &mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Returns true if at least 90% of the inner nodes have a single occupied
+    /// child, meaning the radix structure mostly degenerates into chains.
+    pub fn is_degenerate(&self) -> bool {
+        match self.root {
+            Node::Inner(ref inner) => {
+                let (total, single) = inner.count_single_child();
+                single * 10 >= total * 9
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<T: Rdx> Node<T> {
+    /// Clones the root, which is always an inner node, without cloning values.
+    fn clone_inner(&self) -> Node<T> {
+        match *self {
+            Node::Inner(ref inner) => Node::Inner(inner.clone()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Immutable view of an `RdxTree`, taken by `RdxTree::snapshot`.
+pub struct RdxTreeView<T: Rdx> {
+    tree: RdxTree<T>,
+}
+
+impl<T: Rdx> RdxTreeView<T> {
+    pub fn iter(&self) -> RdxTreeIter<'_, T> {
+        self.tree.iter()
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.tree.contains(x)
+    }
 }
 
 pub struct RdxTreeIter<'a, T: Rdx + 'a> {
@@ -351,6 +696,37 @@ impl<'a, T: Rdx + 'a> Iterator for RdxTreeIter<'a, T> {
     }
 }
 
+pub struct RdxTreePathIter<'a, T: Rdx + 'a> {
+    iters: Vec<iter::Enumerate<slice::Iter<'a, Node<T>>>>,
+    /// Buckets leading to the node whose children `iters.last()` walks.
+    path: Vec<usize>,
+}
+
+impl<'a, T: Rdx + 'a> Iterator for RdxTreePathIter<'a, T> {
+    type Item = (&'a T, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.iters.last_mut()?.next() {
+                Some((_, Node::Free)) => {}
+                Some((bucket, Node::Child(x))) => {
+                    let mut path = self.path.clone();
+                    path.push(bucket);
+                    return Some((x, path));
+                }
+                Some((bucket, Node::Inner(inner))) => {
+                    self.path.push(bucket);
+                    self.iters.push(inner.children.iter().enumerate());
+                }
+                None => {
+                    self.iters.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
 fn print_node<T: fmt::Display + Rdx>(node: &Node<T>, depth: usize) {
     let prefix: String = (0..depth).map(|_| ' ').collect();
     match *node {
@@ -373,11 +749,81 @@ fn print_tree<T: fmt::Display + Rdx>(tree: &RdxTree<T>) {
     print_node(&tree.root, 0);
 }
 
+/// Emits `node` and its subtree as DOT statements, numbering nodes from `next_id`.
+/// Returns the id given to `node`.
+fn dot_node<T: fmt::Display + Rdx>(
+    node: &Node<T>,
+    next_id: &mut usize,
+    out: &mut String,
+) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    match *node {
+        Node::Inner(ref inner) => {
+            out.push_str(&format!("    n{} [label=\"round {}\"];\n", id, inner.round));
+            for (i, c) in inner.children.iter().enumerate() {
+                if let Node::Free = *c {
+                    continue;
+                }
+                let child = dot_node(c, next_id, out);
+                out.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n", id, child, i));
+            }
+        }
+        Node::Child(ref x) => {
+            out.push_str(&format!("    n{} [label=\"{}\", shape=box];\n", id, x));
+        }
+        Node::Free => unreachable!(),
+    }
+    id
+}
+
+
+/// Returns true if `a` comes no later than `b` in the order that radix sort
+/// derives from `get_bucket` and `reverse`.
+fn rdx_le<T: Rdx>(a: &T, b: &T) -> bool {
+    let mut flipped = false;
+    for round in (0..T::cfg_nrounds()).rev() {
+        let (x, y) = (a.get_bucket(round), b.get_bucket(round));
+        if x != y {
+            return (x < y) != flipped;
+        }
+        if T::reverse(round, x) {
+            flipped = !flipped;
+        }
+    }
+    true
+}
+
+/// Counts how many elements of `slice` fall into each bucket in `round`,
+/// without sorting, to show how evenly a radix spreads the data.
+///
+/// Panics if `round` is not below `T::cfg_nrounds()`.
+pub fn rdx_bucket_histogram<T: Rdx>(slice: &[T], round: usize) -> Vec<usize> {
+    assert!(round < T::cfg_nrounds(), "round out of range");
+    let mut counts = vec![0; T::cfg_nbuckets()];
+    for x in slice {
+        counts[x.get_bucket(round)] += 1;
+    }
+    counts
+}
+
+/// Checks that the slice is ordered as `rdxsort` would order it, comparing
+/// buckets from the most significant round down.
+pub fn is_rdx_sorted<T: Rdx>(slice: &[T]) -> bool {
+    slice.windows(2).all(|w| rdx_le(&w[0], &w[1]))
+}
 
 /// Radix Sort implementation for some type
 pub trait RdxSort {
     /// Execute Radix Sort, overwrites (unsorted) content of the type.
     fn rdxsort(&mut self);
+
+    /// Like `rdxsort`, but first scans once for sortedness and leaves input
+    /// that is already sorted untouched, skipping all passes. Defaults to a
+    /// plain `rdxsort` for implementations without such a scan.
+    fn rdxsort_adaptive(&mut self) {
+        self.rdxsort();
+    }
 }
 
 #[inline]
@@ -398,72 +844,316 @@ fn helper_bucket<T, I>(buckets_b: &mut Vec<Vec<T>>, iter: I, cfg_nbuckets: usize
 impl<T> RdxSort for [T] where T: Rdx + Clone
 {
     fn rdxsort(&mut self) {
+        RadixSorter::new().sort(self);
+    }
+
+    fn rdxsort_adaptive(&mut self) {
+        if !is_rdx_sorted(self) {
+            self.rdxsort();
+        }
+    }
+}
+
+impl<T> RdxSort for Vec<T> where [T]: RdxSort
+{
+    fn rdxsort(&mut self) {
+        self.as_mut_slice().rdxsort();
+    }
+
+    fn rdxsort_adaptive(&mut self) {
+        self.as_mut_slice().rdxsort_adaptive();
+    }
+}
+
+impl<T, const N: usize> RdxSort for [T; N] where [T]: RdxSort
+{
+    fn rdxsort(&mut self) {
+        self[..].rdxsort();
+    }
+
+    fn rdxsort_adaptive(&mut self) {
+        self[..].rdxsort_adaptive();
+    }
+}
+
+/// Radix sorter that keeps its bucket allocations between calls, for sorting
+/// many slices without reallocating each time. `rdxsort` uses a fresh one.
+pub struct RadixSorter<T> {
+    buckets_a: Vec<Vec<T>>,
+    buckets_b: Vec<Vec<T>>,
+}
+
+impl<T> Default for RadixSorter<T> {
+    fn default() -> Self {
+        RadixSorter {
+            buckets_a: Vec::new(),
+            buckets_b: Vec::new(),
+        }
+    }
+}
+
+impl<T> RadixSorter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Releases the bucket allocations kept from previous calls.
+    pub fn shrink_to_fit(&mut self) {
+        self.buckets_a = Vec::new();
+        self.buckets_b = Vec::new();
+    }
+}
+
+impl<T> RadixSorter<T> where T: Rdx + Clone
+{
+    /// Sorts `slice` like `rdxsort`, reusing the buckets of earlier calls.
+    pub fn sort(&mut self, slice: &mut [T]) {
         // config
         let cfg_nbuckets = T::cfg_nbuckets();
         let cfg_nrounds = T::cfg_nrounds();
 
-        // early return
-        if cfg_nrounds == 0 {
+        // early return: nothing to do for fewer than two elements, and the
+        // buckets need not be touched at all
+        let n = slice.len();
+        if cfg_nrounds == 0 || n < 2 {
             return;
         }
 
-        let n = self.len();
         let presize = cmp::max(16, (n << 2) / cfg_nbuckets);  // TODO: justify the presize value
-        let mut buckets_a: Vec<Vec<T>> = Vec::with_capacity(cfg_nbuckets);
-        let mut buckets_b: Vec<Vec<T>> = Vec::with_capacity(cfg_nbuckets);
-        for _ in 0..cfg_nbuckets {
-            buckets_a.push(Vec::with_capacity(presize));
-            buckets_b.push(Vec::with_capacity(presize));
+        let buckets_a = &mut self.buckets_a;
+        let buckets_b = &mut self.buckets_b;
+        buckets_a.resize_with(cfg_nbuckets, Vec::new);
+        buckets_b.resize_with(cfg_nbuckets, Vec::new);
+        for bucket in buckets_a.iter_mut().chain(buckets_b.iter_mut()) {
+            bucket.clear();
+            bucket.reserve(presize);
         }
 
-        helper_bucket(&mut buckets_a, self.iter().cloned(), cfg_nbuckets, 0);
+        helper_bucket(buckets_a, slice.iter().cloned(), cfg_nbuckets, 0);
 
         for round in 1..cfg_nrounds {
-            for bucket in &mut buckets_b {
+            for bucket in buckets_b.iter_mut() {
                 bucket.clear();
             }
             for (i, bucket) in buckets_a.iter().enumerate() {
                 if T::reverse(round - 1, i) {
-                    helper_bucket(&mut buckets_b,
+                    helper_bucket(buckets_b,
                                   bucket.iter().rev().cloned(),
                                   cfg_nbuckets,
                                   round);
                 } else {
-                    helper_bucket(&mut buckets_b, bucket.iter().cloned(), cfg_nbuckets, round);
+                    helper_bucket(buckets_b, bucket.iter().cloned(), cfg_nbuckets, round);
                 }
             }
-            mem::swap(&mut buckets_a, &mut buckets_b);
+            mem::swap(buckets_a, buckets_b);
+        }
+        // the previous round's clones must not outlive the call; clearing
+        // keeps the allocations for reuse
+        for bucket in buckets_b.iter_mut() {
+            bucket.clear();
         }
 
+        // Move the values out of the buckets. Assigning through the slots
+        // drops the values they held, which were cloned into the buckets.
         let mut pos = 0;
         for (i, bucket) in buckets_a.iter_mut().enumerate() {
-            assert!(pos + bucket.len() <= self.len(),
+            assert!(pos + bucket.len() <= slice.len(),
                     "bug: a buckets got oversized");
 
+            let slots = slice[pos..pos + bucket.len()].iter_mut();
+            pos += bucket.len();
             if T::reverse(cfg_nrounds - 1, i) {
-                for x in bucket.iter().rev().cloned() {
-                    unsafe {
-                        *self.get_unchecked_mut(pos) = x;
-                    }
-                    pos += 1;
+                for (slot, x) in slots.zip(bucket.drain(..).rev()) {
+                    *slot = x;
                 }
             } else {
-                unsafe {
-                    ptr::copy_nonoverlapping(bucket.as_ptr(),
-                                             self.get_unchecked_mut(pos),
-                                             bucket.len());
+                for (slot, x) in slots.zip(bucket.drain(..)) {
+                    *slot = x;
                 }
-                pos += bucket.len();
             }
         }
 
-        assert!(pos == self.len(), "bug: bucket size does not sum up");
+        assert!(pos == slice.len(), "bug: bucket size does not sum up");
+        debug_assert!(is_rdx_sorted(slice),
+                      "rdxsort: output is unsorted, is the Rdx implementation consistent?");
     }
 }
 
-impl<T> RdxSort for Vec<T> where [T]: RdxSort
+/// Returns a sorted copy of `input`, leaving `input` untouched.
+pub fn rdx_sorted<T: Rdx + Clone>(input: &[T]) -> Vec<T> {
+    let mut v = input.to_vec();
+    v.rdxsort();
+    v
+}
+
+/// Returns the permutation that stably sorts `slice`: `slice[result[0]]` is
+/// its smallest element and so on. The same rounds as `rdxsort` run over the
+/// indices, so the values are neither moved nor cloned.
+pub fn rdx_argsort<T: Rdx>(slice: &[T]) -> Vec<usize> {
+    let cfg_nbuckets = T::cfg_nbuckets();
+    let cfg_nrounds = T::cfg_nrounds();
+    if cfg_nrounds == 0 {
+        return (0..slice.len()).collect();
+    }
+
+    let mut buckets_a: Vec<Vec<usize>> = vec![Vec::new(); cfg_nbuckets];
+    let mut buckets_b: Vec<Vec<usize>> = vec![Vec::new(); cfg_nbuckets];
+    for (i, x) in slice.iter().enumerate() {
+        buckets_a[x.get_bucket(0)].push(i);
+    }
+    for round in 1..cfg_nrounds {
+        for bucket in &mut buckets_b {
+            bucket.clear();
+        }
+        for (b, bucket) in buckets_a.iter().enumerate() {
+            let reverse = T::reverse(round - 1, b);
+            for k in 0..bucket.len() {
+                let i = if reverse { bucket[bucket.len() - 1 - k] } else { bucket[k] };
+                buckets_b[slice[i].get_bucket(round)].push(i);
+            }
+        }
+        mem::swap(&mut buckets_a, &mut buckets_b);
+    }
+
+    let mut result = Vec::with_capacity(slice.len());
+    for (b, bucket) in buckets_a.into_iter().enumerate() {
+        if T::reverse(cfg_nrounds - 1, b) {
+            result.extend(bucket.into_iter().rev());
+        } else {
+            result.extend(bucket);
+        }
+    }
+    result
+}
+
+/// Stably reorders `indices` by the key of the record each one refers to,
+/// running the `rdxsort` rounds over the keys without moving the records.
+pub fn rdxsort_indices_by<R>(indices: &mut [usize], records: &[R], key: impl Fn(&R) -> u32) {
+    let keys: Vec<u32> = indices.iter().map(|&i| key(&records[i])).collect();
+    let order = rdx_argsort(&keys);
+    let sorted: Vec<usize> = order.into_iter().map(|k| indices[k]).collect();
+    indices.copy_from_slice(&sorted);
+}
+
+/// Stably groups the elements of `slice` by `key`, which must map each one to
+/// a bucket below `nbuckets`. Returns the `nbuckets + 1` bucket boundaries, so
+/// that bucket `b` ends up in `slice[bounds[b]..bounds[b + 1]]`.
+///
+/// This is a single counting-sort pass that permutes in place by swapping, so
+/// it can serve as the building block of custom LSD or MSD radix sorts.
+pub fn stable_partition_by<T, F>(slice: &mut [T], nbuckets: usize, key: F) -> Vec<usize>
+    where F: Fn(&T) -> usize
 {
-    fn rdxsort(&mut self) {
-        self.as_mut_slice().rdxsort();
+    let keys: Vec<usize> = slice.iter().map(&key).collect();
+    let mut bounds = vec![0; nbuckets + 1];
+    for &b in &keys {
+        assert!(b < nbuckets, "key returned a bucket >= nbuckets");
+        bounds[b + 1] += 1;
     }
-}
\ No newline at end of file
+    for b in 0..nbuckets {
+        bounds[b + 1] += bounds[b];
+    }
+
+    let mut next = bounds.clone();
+    let mut dest: Vec<usize> = keys
+        .into_iter()
+        .map(|b| {
+            next[b] += 1;
+            next[b] - 1
+        })
+        .collect();
+    for i in 0..slice.len() {
+        while dest[i] != i {
+            let d = dest[i];
+            slice.swap(i, d);
+            dest.swap(i, d);
+        }
+    }
+    bounds
+}
+
+/// Reorders `slice` so that `slice[k]` holds the value `rdxsort` would put
+/// there, with everything before it ordered no later and everything after it
+/// no earlier, like `select_nth_unstable`. Each round, from the most
+/// significant one down, partitions only the bucket that contains index `k`.
+///
+/// Panics if `k` is out of bounds.
+pub fn rdx_select<T: Rdx>(slice: &mut [T], k: usize) {
+    assert!(k < slice.len(), "rdx_select: index {} out of bounds", k);
+    let nbuckets = T::cfg_nbuckets();
+    let (mut lo, mut hi) = (0, slice.len());
+    let mut flipped = false;
+    for round in (0..T::cfg_nrounds()).rev() {
+        if hi - lo == 1 {
+            break;
+        }
+        let bounds = stable_partition_by(&mut slice[lo..hi], nbuckets, |x| {
+            let b = x.get_bucket(round);
+            if flipped { nbuckets - 1 - b } else { b }
+        });
+        let slot = (0..nbuckets).find(|&b| k < lo + bounds[b + 1]).unwrap();
+        let bucket = if flipped { nbuckets - 1 - slot } else { slot };
+        hi = lo + bounds[slot + 1];
+        lo += bounds[slot];
+        if T::reverse(round, bucket) {
+            flipped = !flipped;
+        }
+    }
+}
+
+/// Buckets smaller than this are finished off with insertion sort by `rdxsort_msd`.
+const MSD_INSERTION_THRESHOLD: usize = 16;
+
+/// Most-significant-digit radix sort for byte strings, in lexicographic order.
+///
+/// Pending buckets are kept on an explicit stack instead of being recursed
+/// into, so a long common prefix cannot overflow the call stack. Buckets with
+/// fewer than `MSD_INSERTION_THRESHOLD` elements fall back to insertion sort.
+pub fn rdxsort_msd<S: AsRef<[u8]>>(slice: &mut [S]) {
+    let mut stack = vec![(0, slice.len(), 0)];
+    while let Some((start, end, depth)) = stack.pop() {
+        let part = &mut slice[start..end];
+        if part.len() < MSD_INSERTION_THRESHOLD {
+            for i in 1..part.len() {
+                let mut j = i;
+                while j > 0 && part[j - 1].as_ref()[depth..] > part[j].as_ref()[depth..] {
+                    part.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+            continue;
+        }
+
+        // bucket 0 holds strings that end before `depth`, bucket b + 1 holds byte b
+        let bounds = stable_partition_by(part, 257, |s| {
+            s.as_ref().get(depth).map_or(0, |&b| b as usize + 1)
+        });
+        for b in 1..257 {
+            if bounds[b + 1] - bounds[b] > 1 {
+                stack.push((start + bounds[b], start + bounds[b + 1], depth + 1));
+            }
+        }
+    }
+}
+
+/// Partially sorts `slice` by the top `bytes` bytes of each value, using
+/// that many MSD passes. Values sharing those bytes keep their original
+/// relative order, so `bytes >= 4` amounts to a full stable sort.
+pub fn rdxsort_prefix(slice: &mut [u32], bytes: usize) {
+    let bytes = cmp::min(bytes, 4);
+    let mut stack = vec![(0, slice.len(), 0)];
+    while let Some((start, end, depth)) = stack.pop() {
+        if depth == bytes {
+            continue;
+        }
+        let shift = 24 - 8 * depth;
+        let bounds = stable_partition_by(&mut slice[start..end], 256, |&x| {
+            (x >> shift) as usize & 0xff
+        });
+        for b in 0..256 {
+            if bounds[b + 1] - bounds[b] > 1 {
+                stack.push((start + bounds[b], start + bounds[b + 1], depth + 1));
+            }
+        }
+    }
+}
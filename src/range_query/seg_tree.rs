@@ -1,59 +1,288 @@
-pub struct SegmentTree {
-    data: Vec<i32>,
-    tree: Vec<Option<i32>>,
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::ops::BitXor;
+
+/// Errors returned by segment tree queries and updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryError {
+    /// An index lies outside the underlying array.
+    OutOfBounds,
+    /// The left end of a range lies past its right end.
+    InvalidRange,
+}
+
+/// Element types that a `SegmentTree` can sum. Implemented for the primitive
+/// numeric types, and with the `num-traits` feature also for `Wrapping<T>`
+/// wherever that implements `num_traits::Num`.
+pub trait SegmentValue: Copy + PartialEq + core::ops::Add<Output = Self> {
+    /// The additive identity.
+    fn zero() -> Self;
+}
+
+macro_rules! impl_segment_value {
+    ($($t:ty),*) => {
+        $(impl SegmentValue for $t {
+            fn zero() -> Self {
+                0 as $t
+            }
+        })*
+    };
+}
+
+impl_segment_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+#[cfg(feature = "num-traits")]
+impl<T> SegmentValue for core::num::Wrapping<T>
+where
+    core::num::Wrapping<T>: num_traits::Num + Copy,
+{
+    fn zero() -> Self {
+        num_traits::Zero::zero()
+    }
+}
+
+pub struct SegmentTree<T = i32> {
+    /// `data` and `tree` are shared with snapshots and copied on the first
+    /// write after a `snapshot` call.
+    data: Rc<Vec<T>>,
+    tree: Rc<Vec<Option<T>>>,
+    on_change: Option<Box<dyn FnMut(usize, T)>>,
+    /// Merges two child values; addition unless built with `with_combine`.
+    combine: fn(T, T) -> T,
+    /// Additions by `range_add` not yet passed down to each node's children,
+    /// present only for trees built with lazy support.
+    lazy: Option<Rc<Vec<T>>>,
+    /// Whether `combine` sums, so that adding `delta` to every element of a
+    /// node adds it to the node once per element rather than once.
+    sum_like: bool,
+}
+
+/// The original `i32` segment tree.
+pub type I32SegmentTree = SegmentTree<i32>;
+
+fn left_child(index: usize) -> usize {
+    2 * index + 1
+}
+
+fn right_child(index: usize) -> usize {
+    2 * index + 2
 }
 
 // https://www.zhihu.com/people/Classicalcastle
-impl SegmentTree {
-    pub fn new_segment_tree(arr: Vec<i32>) -> SegmentTree {
+impl<T: SegmentValue> SegmentTree<T> {
+    pub fn new_segment_tree(arr: Vec<T>) -> SegmentTree<T> {
+        Self::with_combine(arr, |a, b| a + b)
+    }
+
+    /// Like `new_segment_tree`, but nodes are merged with `combine` instead of
+    /// `+`. `combine` must be associative. It need not have `T::zero()` as
+    /// identity, as with min or max, since queries only fall back to zero for
+    /// nodes that were never built; call `build` before querying.
+    pub fn with_combine(arr: Vec<T>, combine: fn(T, T) -> T) -> SegmentTree<T> {
         let data_len = arr.len();
         Self {
-            data: arr,
-            tree: vec![None; 4 * data_len],
+            data: Rc::new(arr),
+            tree: Rc::new(vec![None; 4 * data_len]),
+            on_change: None,
+            combine,
+            lazy: None,
+            sum_like: false,
+        }
+    }
+
+    /// Builds a tree over `f(0), f(1), ..., f(n - 1)`. Unlike
+    /// `new_segment_tree`, the result is ready to query.
+    pub fn new_from_fn(n: usize, f: impl Fn(usize) -> T) -> SegmentTree<T> {
+        let mut tree = Self::new_segment_tree((0..n).map(f).collect());
+        if n > 0 {
+            tree.build();
+        }
+        tree
+    }
+
+    /// Builds a ready-to-query tree over the prefix sums of `diffs`, so that
+    /// element `i` is `diffs[0] + ... + diffs[i]`.
+    pub fn from_diff(mut diffs: Vec<T>) -> SegmentTree<T> {
+        for i in 1..diffs.len() {
+            diffs[i] = diffs[i - 1] + diffs[i];
+        }
+        let n = diffs.len();
+        let mut tree = Self::new_segment_tree(diffs);
+        if n > 0 {
+            tree.build();
+        }
+        tree
+    }
+
+    /// Builds a tree summing `key` over `records`, which stay accessible
+    /// through the returned tree.
+    pub fn from_records<R>(
+        records: Vec<R>,
+        key: impl Fn(&R) -> T + 'static,
+    ) -> RecordSegmentTree<R, T> {
+        let tree = Self::new_from_fn(records.len(), |i| key(&records[i]));
+        RecordSegmentTree {
+            records,
+            key: Box::new(key),
+            tree,
         }
     }
 
-    fn left_child(index: usize) -> usize {
-        return 2 * index + 1;
+    /// Returns a read-only view of the current state. Taking it is O(1): the
+    /// arrays are shared until the next write to `self`, which copies them.
+    pub fn snapshot(&self) -> SegmentTreeSnapshot<T> {
+        SegmentTreeSnapshot {
+            tree: SegmentTree {
+                data: self.data.clone(),
+                tree: self.tree.clone(),
+                on_change: None,
+                combine: self.combine,
+                lazy: self.lazy.clone(),
+                sum_like: self.sum_like,
+            },
+        }
     }
 
-    fn right_child(index: usize) -> usize {
-        return 2 * index + 2;
+    /// Registers a callback invoked by `set` with `(index, new_value)` whenever
+    /// a leaf actually changes value. Replaces any previously registered callback.
+    pub fn on_change<F: FnMut(usize, T) + 'static>(&mut self, f: F) {
+        self.on_change = Some(Box::new(f));
     }
 
-    pub fn get(&self, index: usize) -> Option<i32> {
+    pub fn get(&self, index: usize) -> Option<T> {
         if index >= self.data.len() {
             return None;
         }
+        if self.lazy.is_some() {
+            return Some(self.recursion_query(0, 0, self.data.len() - 1, index, index, T::zero()));
+        }
         return Some(self.data[index]);
     }
 
+    /// Returns the raw values data[l..=r], or None if the range is empty or out of bounds.
+    /// The raw values do not include additions made by `range_add`.
+    pub fn values(&self, l: usize, r: usize) -> Option<&[T]> {
+        if l > r || r >= self.data.len() {
+            return None;
+        }
+        Some(&self.data[l..=r])
+    }
+
+    /// Returns the aggregate stored at `tree_index` of the internal array, or
+    /// None if that node is unused or the tree is not built. Node 0 covers the
+    /// whole array and node `i` has children `2 * i + 1` and `2 * i + 2`.
+    pub fn node_value(&self, tree_index: usize) -> Option<T> {
+        self.tree.get(tree_index).cloned().flatten()
+    }
+
+    /// Returns the length of the internal node array, four times the number
+    /// of elements.
+    pub fn tree_len(&self) -> usize {
+        self.tree.len()
+    }
+
     pub fn build(&mut self) {
         self.build_segment_tree(0, 0, self.data.len() - 1);
     }
 
+    /// Shortens or extends the array to `new_len` elements, filling new ones
+    /// with `fill`, and rebuilds the tree in O(n). Additions pending from
+    /// `range_add` are folded into the values first.
+    pub fn resize(&mut self, new_len: usize, fill: T) {
+        let mut values: Vec<T> = match self.lazy {
+            Some(_) => (0..self.data.len()).map(|i| self.get(i).unwrap()).collect(),
+            None => self.data.as_ref().clone(),
+        };
+        values.resize(new_len, fill);
+        self.data = Rc::new(values);
+        self.tree = Rc::new(vec![None; 4 * new_len]);
+        if let Some(ref mut lazy) = self.lazy {
+            *lazy = Rc::new(vec![T::zero(); 4 * new_len]);
+        }
+        if new_len > 0 {
+            self.build();
+        }
+    }
+
+    /// Appends `value` at the end and rebuilds the tree.
+    pub fn push(&mut self, value: T) {
+        self.resize(self.data.len() + 1, value);
+    }
+
+    /// Removes the last element and rebuilds the tree, returning the element
+    /// or None if the array is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.get(self.data.len().checked_sub(1)?)?;
+        self.resize(self.data.len() - 1, last);
+        Some(last)
+    }
+
     fn build_segment_tree(&mut self, tree_index: usize, left: usize, right: usize) {
         if left == right {
-            self.tree[tree_index] = Some(self.data[left]);
+            Rc::make_mut(&mut self.tree)[tree_index] = Some(self.data[left]);
             return;
         }
-        let left_tree_index = Self::left_child(tree_index);
-        let right_tree_index = Self::right_child(tree_index);
+        let left_tree_index = left_child(tree_index);
+        let right_tree_index = right_child(tree_index);
         let mid = (right - left) / 2 + left;
         self.build_segment_tree(left_tree_index, left, mid);
         self.build_segment_tree(right_tree_index, mid + 1, right);
         if let Some(l) = self.tree[left_tree_index] {
             if let Some(r) = self.tree[right_tree_index] {
-                self.tree[tree_index] = Some(l + r)
+                Rc::make_mut(&mut self.tree)[tree_index] = Some((self.combine)(l, r))
             }
         }
     }
-    pub fn query(&self, l: usize, r: usize) -> Result<i32, &'static str> {
-        if l > self.data.len() || r > self.data.len() || l > r {
-            return Err("Error");
+    pub fn query(&self, l: usize, r: usize) -> Result<T, QueryError> {
+        if l >= self.data.len() || r >= self.data.len() {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(self.recursion_query(0, 0, self.data.len() - 1, l, r, T::zero()))
+    }
+    /// Folds `combine` over data[l..=r], starting from `identity`. The stored
+    /// sums are not used, so any statistic can be computed in O(r - l) time.
+    pub fn query_with<F: Fn(T, T) -> T>(
+        &self,
+        l: usize,
+        r: usize,
+        combine: F,
+        identity: T,
+    ) -> Result<T, QueryError> {
+        if l >= self.data.len() || r >= self.data.len() {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(self.data[l..=r]
+            .iter()
+            .fold(identity, |acc, &x| combine(acc, x)))
+    }
+
+    /// Counts the elements of data[l..=r] equal to `target` by scanning them,
+    /// in O(r - l) time.
+    pub fn count_value(&self, l: usize, r: usize, target: T) -> Result<usize, QueryError> {
+        if l >= self.data.len() || r >= self.data.len() {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
         }
-        Ok(self.recursion_query(0, 0, self.data.len() - 1, l, r))
+        Ok(self.data[l..=r].iter().filter(|&&x| x == target).count())
+    }
+
+    /// Answers each (l, r) range query in order, as if by calling `query`.
+    pub fn query_many(&self, ranges: &[(usize, usize)]) -> Vec<Result<T, QueryError>> {
+        ranges.iter().map(|&(l, r)| self.query(l, r)).collect()
     }
+
+    /// `pending` is the sum of the lazy additions of the ancestors, which
+    /// still have to be applied to the nodes covering the query.
     fn recursion_query(
         &self,
         tree_index: usize,
@@ -61,43 +290,161 @@ impl SegmentTree {
         r: usize,
         query_left: usize,
         query_right: usize,
-    ) -> i32 {
+        mut pending: T,
+    ) -> T {
         if l == query_left && r == query_right {
             if let Some(d) = self.tree[tree_index] {
+                if self.lazy.is_some() {
+                    return self.apply_add(d, pending, r - l + 1);
+                }
                 return d;
             }
-            return 0;
+            return T::zero();
+        }
+        if let Some(ref lazy) = self.lazy {
+            pending = pending + lazy[tree_index];
         }
         let mid = l + (r - l) / 2;
-        let l_t_ind = Self::left_child(tree_index);
-        let r_t_ind = Self::right_child(tree_index);
+        let l_t_ind = left_child(tree_index);
+        let r_t_ind = right_child(tree_index);
 
         if query_left >= mid + 1 {
-            return self.recursion_query(r_t_ind, mid + 1, r, query_left, query_right);
+            return self.recursion_query(r_t_ind, mid + 1, r, query_left, query_right, pending);
         } else if query_right <= mid {
-            return self.recursion_query(l_t_ind, l, mid, query_left, query_right);
+            return self.recursion_query(l_t_ind, l, mid, query_left, query_right, pending);
+        }
+        let l_res = self.recursion_query(l_t_ind, l, mid, query_left, mid, pending);
+        let r_res = self.recursion_query(r_t_ind, mid + 1, r, mid + 1, query_right, pending);
+        (self.combine)(l_res, r_res)
+    }
+
+    /// Adds `delta` to every element of data[l..=r]. Each addition waits at
+    /// the O(log n) nodes covering the range instead of reaching the leaves,
+    /// so raw values (`values`, `query_with`, `count_value`) do not see it and
+    /// no `on_change` callback runs.
+    ///
+    /// Panics unless the tree was built by a `SegmentTreeBuilder` with lazy
+    /// support.
+    pub fn range_add(&mut self, l: usize, r: usize, delta: T) -> Result<(), QueryError> {
+        assert!(
+            self.lazy.is_some(),
+            "range_add needs a tree built with lazy support"
+        );
+        if l >= self.data.len() || r >= self.data.len() {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        self.recursion_add(0, 0, self.data.len() - 1, l, r, delta);
+        Ok(())
+    }
+
+    fn recursion_add(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        add_left: usize,
+        add_right: usize,
+        delta: T,
+    ) {
+        if l == add_left && r == add_right {
+            self.add_to_node(tree_index, delta, r - l + 1);
+            return;
+        }
+        let mid = l + (r - l) / 2;
+        let l_t_ind = left_child(tree_index);
+        let r_t_ind = right_child(tree_index);
+        if add_left > mid {
+            self.recursion_add(r_t_ind, mid + 1, r, add_left, add_right, delta);
+        } else if add_right <= mid {
+            self.recursion_add(l_t_ind, l, mid, add_left, add_right, delta);
+        } else {
+            self.recursion_add(l_t_ind, l, mid, add_left, mid, delta);
+            self.recursion_add(r_t_ind, mid + 1, r, mid + 1, add_right, delta);
+        }
+        if let (Some(l_d), Some(r_d)) = (self.tree[l_t_ind], self.tree[r_t_ind]) {
+            let own = self.lazy.as_ref().unwrap()[tree_index];
+            let value = self.apply_add((self.combine)(l_d, r_d), own, r - l + 1);
+            Rc::make_mut(&mut self.tree)[tree_index] = Some(value);
+        }
+    }
+
+    /// Adds `delta` to each of the `len` elements under `tree_index`,
+    /// recording it there as pending for the node's children.
+    fn add_to_node(&mut self, tree_index: usize, delta: T, len: usize) {
+        if let Some(d) = self.tree[tree_index] {
+            let value = self.apply_add(d, delta, len);
+            Rc::make_mut(&mut self.tree)[tree_index] = Some(value);
+        }
+        let lazy = Rc::make_mut(self.lazy.as_mut().unwrap());
+        lazy[tree_index] = lazy[tree_index] + delta;
+    }
+
+    /// Hands the pending addition of `tree_index`, covering [l, r], down to
+    /// its children.
+    fn push_down(&mut self, tree_index: usize, l: usize, r: usize) {
+        let delta = match self.lazy {
+            Some(ref lazy) if lazy[tree_index] != T::zero() => lazy[tree_index],
+            _ => return,
+        };
+        let mid = l + (r - l) / 2;
+        self.add_to_node(left_child(tree_index), delta, mid - l + 1);
+        self.add_to_node(right_child(tree_index), delta, r - mid);
+        Rc::make_mut(self.lazy.as_mut().unwrap())[tree_index] = T::zero();
+    }
+
+    /// Returns the value of a node of `len` elements after adding `delta` to
+    /// each of them.
+    fn apply_add(&self, value: T, delta: T, len: usize) -> T {
+        if self.sum_like {
+            value + repeat_add(delta, len)
+        } else {
+            value + delta
         }
-        let l_res = self.recursion_query(l_t_ind, l, mid, query_left, mid);
-        let r_res = self.recursion_query(r_t_ind, mid + 1, r, mid + 1, query_right);
-        l_res + r_res
     }
-    pub fn set(&mut self, index: usize, e: i32) -> Result<(), &'static str> {
+
+    pub fn set(&mut self, index: usize, e: T) -> Result<(), QueryError> {
         if index >= self.data.len() {
-            return Err("Error");
+            return Err(QueryError::OutOfBounds);
         }
-        self.data[index] = e;
+        let changed = self.get(index) != Some(e);
+        Rc::make_mut(&mut self.data)[index] = e;
         self.recursion_set(0, 0, self.data.len() - 1, index, e);
+        if changed {
+            if let Some(f) = self.on_change.as_mut() {
+                f(index, e);
+            }
+        }
         Ok(())
     }
 
-    fn recursion_set(&mut self, index_tree: usize, l: usize, r: usize, index: usize, e: i32) {
+    /// Sets data[index] to `e` only if `pred(current, e)` holds, and returns
+    /// whether it did.
+    pub fn update_if(
+        &mut self,
+        index: usize,
+        e: T,
+        pred: impl Fn(T, T) -> bool,
+    ) -> Result<bool, QueryError> {
+        let current = self.get(index).ok_or(QueryError::OutOfBounds)?;
+        if !pred(current, e) {
+            return Ok(false);
+        }
+        self.set(index, e)?;
+        Ok(true)
+    }
+
+    fn recursion_set(&mut self, index_tree: usize, l: usize, r: usize, index: usize, e: T) {
         if l == r {
-            self.tree[index_tree] = Some(e);
+            Rc::make_mut(&mut self.tree)[index_tree] = Some(e);
             return;
         }
-        let mid = l + (r - 1) / 2;
-        let left_child = Self::left_child(index_tree);
-        let right_child = Self::right_child(index_tree);
+        self.push_down(index_tree, l, r);
+        let mid = l + (r - l) / 2;
+        let left_child = left_child(index_tree);
+        let right_child = right_child(index_tree);
         if index >= mid + 1 {
             self.recursion_set(right_child, mid + 1, r, index, e);
         } else {
@@ -105,8 +452,1664 @@ impl SegmentTree {
         }
         if let Some(l_d) = self.tree[left_child] {
             if let Some(r_d) = self.tree[right_child] {
-                self.tree[index_tree] = Some(l_d + r_d);
+                Rc::make_mut(&mut self.tree)[index_tree] = Some((self.combine)(l_d, r_d));
+            }
+        }
+    }
+}
+
+impl SegmentTree<i32> {
+    /// Builds a ready-to-query tree whose sums saturate at `i32::MAX` and
+    /// `i32::MIN` instead of overflowing. Saturation is not associative once
+    /// both bounds are hit, so such a sum depends on how the range splits.
+    pub fn new_saturating(arr: Vec<i32>) -> SegmentTree<i32> {
+        let n = arr.len();
+        let mut tree = Self::with_combine(arr, i32::saturating_add);
+        if n > 0 {
+            tree.build();
+        }
+        tree
+    }
+
+    /// Counts the raw values of data[l..=r] in buckets of `bucket_width`,
+    /// scanning them in O(r - l) time. Bucket `k` holds the values in
+    /// `[lo + k * bucket_width, lo + (k + 1) * bucket_width)`, where `lo` is
+    /// the smallest value in the range, and the last bucket holds the largest.
+    /// Every bucket in between gets a counter, so a narrow width over widely
+    /// spread values allocates a lot; `histogram_sparse` avoids that.
+    ///
+    /// Panics if `bucket_width` is not positive.
+    pub fn histogram(
+        &self,
+        l: usize,
+        r: usize,
+        bucket_width: i32,
+    ) -> Result<Vec<usize>, QueryError> {
+        let (values, bucket) = self.histogram_buckets(l, r, bucket_width)?;
+        let last = values.iter().map(|&x| bucket(x)).max().unwrap();
+        let mut counts = vec![0; last + 1];
+        for &x in values {
+            counts[bucket(x)] += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Like `histogram`, but returns only the nonempty buckets, keyed by their
+    /// index. Takes O((r - l) log(r - l)) time and space, however spread out
+    /// the values are.
+    pub fn histogram_sparse(
+        &self,
+        l: usize,
+        r: usize,
+        bucket_width: i32,
+    ) -> Result<BTreeMap<usize, usize>, QueryError> {
+        let (values, bucket) = self.histogram_buckets(l, r, bucket_width)?;
+        let mut counts = BTreeMap::new();
+        for &x in values {
+            *counts.entry(bucket(x)).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Checks the arguments of the histogram methods, returning data[l..=r]
+    /// and the map from a value to its bucket.
+    fn histogram_buckets(
+        &self,
+        l: usize,
+        r: usize,
+        bucket_width: i32,
+    ) -> Result<(&[i32], impl Fn(i32) -> usize), QueryError> {
+        assert!(bucket_width > 0, "bucket_width must be positive");
+        if l >= self.data.len() || r >= self.data.len() {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        let values = &self.data[l..=r];
+        let lo = i64::from(*values.iter().min().unwrap());
+        let bucket = move |x: i32| ((i64::from(x) - lo) / i64::from(bucket_width)) as usize;
+        Ok((values, bucket))
+    }
+}
+
+/// Range queries and point updates over `i32`, whatever the combining
+/// operation. Object safe, so trees with different operations can be stored
+/// together as `Box<dyn RangeTree>`.
+pub trait RangeTree {
+    /// Combines the values at indices `l..=r`.
+    fn query(&self, l: usize, r: usize) -> Result<i32, QueryError>;
+    /// Sets the value at index `i` to `v`.
+    fn update(&mut self, i: usize, v: i32) -> Result<(), QueryError>;
+}
+
+impl RangeTree for SegmentTree<i32> {
+    fn query(&self, l: usize, r: usize) -> Result<i32, QueryError> {
+        SegmentTree::query(self, l, r)
+    }
+
+    fn update(&mut self, i: usize, v: i32) -> Result<(), QueryError> {
+        self.set(i, v)
+    }
+}
+
+impl<T: SegmentValue + BitXor<Output = T>> SegmentTree<T> {
+    /// Builds a tree whose nodes hold the XOR of their range, so that `query`
+    /// returns data[l] ^ ... ^ data[r]. Unlike `new_segment_tree`, the result
+    /// is ready to query.
+    pub fn new_xor(arr: Vec<T>) -> SegmentTree<T> {
+        let n = arr.len();
+        let mut tree = Self::with_combine(arr, |a, b| a ^ b);
+        if n > 0 {
+            tree.build();
+        }
+        tree
+    }
+}
+
+/// Returns `x + x + ... + x` with `n` terms, using O(log n) additions.
+fn repeat_add<T: SegmentValue>(x: T, mut n: usize) -> T {
+    let (mut result, mut power) = (T::zero(), x);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result + power;
+        }
+        n >>= 1;
+        if n > 0 {
+            power = power + power;
+        }
+    }
+    result
+}
+
+/// How a `SegmentTreeBuilder` combines the values of a range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Sum,
+    Min,
+    Max,
+}
+
+/// Configures and builds a `SegmentTree`, e.g.
+/// `SegmentTreeBuilder::new().data(v).op(Op::Max).with_lazy(true).build()`.
+pub struct SegmentTreeBuilder<T = i32> {
+    data: Vec<T>,
+    op: Op,
+    lazy: bool,
+}
+
+impl<T> Default for SegmentTreeBuilder<T> {
+    fn default() -> Self {
+        SegmentTreeBuilder {
+            data: Vec::new(),
+            op: Op::Sum,
+            lazy: false,
+        }
+    }
+}
+
+impl<T: SegmentValue + PartialOrd> SegmentTreeBuilder<T> {
+    /// Starts from an empty sum tree without lazy support.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn data(mut self, data: Vec<T>) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn op(mut self, op: Op) -> Self {
+        self.op = op;
+        self
+    }
+
+    /// Enables `SegmentTree::range_add`.
+    pub fn with_lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Builds the tree, ready to query.
+    pub fn build(self) -> SegmentTree<T> {
+        let combine: fn(T, T) -> T = match self.op {
+            Op::Sum => |a, b| a + b,
+            Op::Min => |a, b| if b < a { b } else { a },
+            Op::Max => |a, b| if a < b { b } else { a },
+        };
+        let n = self.data.len();
+        let mut tree = SegmentTree::with_combine(self.data, combine);
+        tree.sum_like = self.op == Op::Sum;
+        if self.lazy {
+            tree.lazy = Some(Rc::new(vec![T::zero(); tree.tree.len()]));
+        }
+        if n > 0 {
+            tree.build();
+        }
+        tree
+    }
+}
+
+/// Immutable view of a `SegmentTree` taken by `SegmentTree::snapshot`. Later
+/// updates to the tree do not show through, and clones share the same arrays.
+pub struct SegmentTreeSnapshot<T = i32> {
+    tree: SegmentTree<T>,
+}
+
+impl<T: SegmentValue> Clone for SegmentTreeSnapshot<T> {
+    fn clone(&self) -> Self {
+        self.tree.snapshot()
+    }
+}
+
+impl<T: SegmentValue> SegmentTreeSnapshot<T> {
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.tree.get(index)
+    }
+
+    pub fn query(&self, l: usize, r: usize) -> Result<T, QueryError> {
+        self.tree.query(l, r)
+    }
+}
+
+/// Segment tree over a numeric key of each record, built by
+/// `SegmentTree::from_records`.
+pub struct RecordSegmentTree<R, T = i32> {
+    records: Vec<R>,
+    key: Box<dyn Fn(&R) -> T>,
+    tree: SegmentTree<T>,
+}
+
+impl<R, T: SegmentValue> RecordSegmentTree<R, T> {
+    pub fn get(&self, index: usize) -> Option<&R> {
+        self.records.get(index)
+    }
+
+    /// Returns the sum of the keys of records[l..=r].
+    pub fn query(&self, l: usize, r: usize) -> Result<T, QueryError> {
+        self.tree.query(l, r)
+    }
+
+    /// Replaces the record at `index`, returning the old one.
+    pub fn set(&mut self, index: usize, record: R) -> Result<R, QueryError> {
+        self.tree.set(index, (self.key)(&record))?;
+        Ok(core::mem::replace(&mut self.records[index], record))
+    }
+}
+
+/// Sum tree with the same results as `SegmentTree`, stored bottom-up in
+/// exactly `2 * n.next_power_of_two()` slots instead of `4 * n`. Leaf `i`
+/// lives at `size + i` and node `k` has children `2k` and `2k + 1`.
+pub struct CompactSegmentTree<T = i32> {
+    size: usize,
+    len: usize,
+    tree: Vec<T>,
+}
+
+impl<T: SegmentValue> CompactSegmentTree<T> {
+    pub fn new(arr: Vec<T>) -> CompactSegmentTree<T> {
+        let len = arr.len();
+        let size = len.next_power_of_two();
+        let mut tree = vec![T::zero(); 2 * size];
+        tree[size..size + len].copy_from_slice(&arr);
+        for k in (1..size).rev() {
+            tree[k] = tree[2 * k] + tree[2 * k + 1];
+        }
+        Self { size, len, tree }
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.tree[self.size + index])
+    }
+
+    /// Returns the sum of the elements in [l, r].
+    pub fn query(&self, l: usize, r: usize) -> Result<T, QueryError> {
+        if l >= self.len || r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        let (mut l, mut r) = (l + self.size, r + self.size + 1);
+        let (mut l_res, mut r_res) = (T::zero(), T::zero());
+        while l < r {
+            if l & 1 == 1 {
+                l_res = l_res + self.tree[l];
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                r_res = self.tree[r] + r_res;
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        Ok(l_res + r_res)
+    }
+
+    pub fn set(&mut self, index: usize, e: T) -> Result<(), QueryError> {
+        if index >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        let mut k = self.size + index;
+        self.tree[k] = e;
+        while k > 1 {
+            k >>= 1;
+            self.tree[k] = self.tree[2 * k] + self.tree[2 * k + 1];
+        }
+        Ok(())
+    }
+}
+
+/// Minimum tree that also counts how often the minimum occurs. It uses the
+/// same bottom-up layout as `CompactSegmentTree`, with `None` as the
+/// identity for the padding leaves.
+pub struct MinCountSegmentTree<T = i32> {
+    size: usize,
+    len: usize,
+    tree: Vec<Option<(T, usize)>>,
+}
+
+fn combine_min_count<T: Ord + Copy>(
+    a: Option<(T, usize)>,
+    b: Option<(T, usize)>,
+) -> Option<(T, usize)> {
+    match (a, b) {
+        (Some((x, cx)), Some((y, cy))) => Some(match x.cmp(&y) {
+            core::cmp::Ordering::Less => (x, cx),
+            core::cmp::Ordering::Greater => (y, cy),
+            core::cmp::Ordering::Equal => (x, cx + cy),
+        }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl<T: Ord + Copy> MinCountSegmentTree<T> {
+    pub fn new(arr: Vec<T>) -> MinCountSegmentTree<T> {
+        let len = arr.len();
+        let size = len.next_power_of_two();
+        let mut tree = vec![None; 2 * size];
+        for (i, x) in arr.into_iter().enumerate() {
+            tree[size + i] = Some((x, 1));
+        }
+        for k in (1..size).rev() {
+            tree[k] = combine_min_count(tree[2 * k], tree[2 * k + 1]);
+        }
+        Self { size, len, tree }
+    }
+
+    /// Returns the minimum of the elements in [l, r] and its number of
+    /// occurrences, or None if the range is empty or out of bounds.
+    pub fn query_min_count(&self, l: usize, r: usize) -> Option<(T, usize)> {
+        if l > r || r >= self.len {
+            return None;
+        }
+        let (mut l, mut r) = (l + self.size, r + self.size + 1);
+        let mut res = None;
+        while l < r {
+            if l & 1 == 1 {
+                res = combine_min_count(res, self.tree[l]);
+                l += 1;
             }
+            if r & 1 == 1 {
+                r -= 1;
+                res = combine_min_count(res, self.tree[r]);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        res
+    }
+
+    pub fn set(&mut self, index: usize, e: T) -> Result<(), QueryError> {
+        if index >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        let mut k = self.size + index;
+        self.tree[k] = Some((e, 1));
+        while k > 1 {
+            k >>= 1;
+            self.tree[k] = combine_min_count(self.tree[2 * k], self.tree[2 * k + 1]);
+        }
+        Ok(())
+    }
+}
+
+/// Sum tree over residues modulo `m`, supporting lazy range multiplication
+/// and range addition. Each pending tag (mul, add) maps x to x * mul + add,
+/// so a later tag (mul2, add2) composes into (mul * mul2, add * mul2 + add2).
+pub struct ModSegmentTree {
+    m: u64,
+    len: usize,
+    sum: Vec<u64>,
+    tag: Vec<(u64, u64)>,
+}
+
+impl ModSegmentTree {
+    /// Builds the tree over `arr`, reducing every value modulo `m`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is zero or exceeds 2^32, since products of residues must fit in a u64.
+    pub fn new(arr: Vec<u64>, m: u64) -> ModSegmentTree {
+        assert!(m > 0 && m <= 1 << 32, "modulus must be in 1..=2^32");
+        let mut tree = Self {
+            m,
+            len: arr.len(),
+            sum: vec![0; 4 * arr.len()],
+            tag: vec![(1, 0); 4 * arr.len()],
+        };
+        if !arr.is_empty() {
+            tree.build(0, 0, arr.len() - 1, &arr);
+        }
+        tree
+    }
+
+    fn build(&mut self, tree_index: usize, l: usize, r: usize, arr: &[u64]) {
+        if l == r {
+            self.sum[tree_index] = arr[l] % self.m;
+            return;
+        }
+        let mid = l + (r - l) / 2;
+        let (l_t_ind, r_t_ind) = (left_child(tree_index), right_child(tree_index));
+        self.build(l_t_ind, l, mid, arr);
+        self.build(r_t_ind, mid + 1, r, arr);
+        self.sum[tree_index] = (self.sum[l_t_ind] + self.sum[r_t_ind]) % self.m;
+    }
+
+    fn apply(&mut self, tree_index: usize, size: usize, (mul, add): (u64, u64)) {
+        let m = self.m;
+        let size = (size as u64) % m;
+        self.sum[tree_index] = (self.sum[tree_index] * mul % m + add * size % m) % m;
+        let (old_mul, old_add) = self.tag[tree_index];
+        self.tag[tree_index] = (old_mul * mul % m, (old_add * mul % m + add) % m);
+    }
+
+    fn push_down(&mut self, tree_index: usize, l: usize, r: usize) {
+        let tag = core::mem::replace(&mut self.tag[tree_index], (1, 0));
+        if tag != (1, 0) {
+            let mid = l + (r - l) / 2;
+            self.apply(left_child(tree_index), mid - l + 1, tag);
+            self.apply(right_child(tree_index), r - mid, tag);
+        }
+    }
+
+    fn recursion_update(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+        tag: (u64, u64),
+    ) {
+        if query_left <= l && r <= query_right {
+            self.apply(tree_index, r - l + 1, tag);
+            return;
+        }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let l_t_ind = left_child(tree_index);
+        let r_t_ind = right_child(tree_index);
+        if query_left <= mid {
+            self.recursion_update(l_t_ind, l, mid, query_left, query_right, tag);
+        }
+        if query_right > mid {
+            self.recursion_update(r_t_ind, mid + 1, r, query_left, query_right, tag);
+        }
+        self.sum[tree_index] = (self.sum[l_t_ind] + self.sum[r_t_ind]) % self.m;
+    }
+
+    fn recursion_query(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+    ) -> u64 {
+        if query_left <= l && r <= query_right {
+            return self.sum[tree_index];
         }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let mut res = 0;
+        if query_left <= mid {
+            res += self.recursion_query(left_child(tree_index), l, mid, query_left, query_right);
+        }
+        if query_right > mid {
+            res +=
+                self.recursion_query(right_child(tree_index), mid + 1, r, query_left, query_right);
+        }
+        res % self.m
+    }
+
+    fn update(&mut self, l: usize, r: usize, tag: (u64, u64)) -> Result<(), QueryError> {
+        if r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        self.recursion_update(0, 0, self.len - 1, l, r, tag);
+        Ok(())
+    }
+
+    /// Adds v to every element in [l, r], modulo m.
+    pub fn range_add(&mut self, l: usize, r: usize, v: u64) -> Result<(), QueryError> {
+        let v = v % self.m;
+        self.update(l, r, (1, v))
+    }
+
+    /// Multiplies every element in [l, r] by v, modulo m.
+    pub fn range_mul(&mut self, l: usize, r: usize, v: u64) -> Result<(), QueryError> {
+        let v = v % self.m;
+        self.update(l, r, (v, 0))
+    }
+
+    /// Returns the sum of the elements in [l, r], modulo m.
+    pub fn query(&mut self, l: usize, r: usize) -> Result<u64, QueryError> {
+        if r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(self.recursion_query(0, 0, self.len - 1, l, r))
+    }
+}
+
+/// Sum tree supporting lazy range assignment. A pending assignment on a node
+/// overrides everything below it, so pushing it down simply replaces the
+/// children's values and pending assignments.
+pub struct AssignSumSegmentTree {
+    len: usize,
+    sum: Vec<i64>,
+    assign: Vec<Option<i64>>,
+}
+
+impl AssignSumSegmentTree {
+    pub fn new(arr: Vec<i64>) -> AssignSumSegmentTree {
+        let mut tree = Self {
+            len: arr.len(),
+            sum: vec![0; 4 * arr.len()],
+            assign: vec![None; 4 * arr.len()],
+        };
+        if !arr.is_empty() {
+            tree.build(0, 0, arr.len() - 1, &arr);
+        }
+        tree
+    }
+
+    fn build(&mut self, tree_index: usize, l: usize, r: usize, arr: &[i64]) {
+        if l == r {
+            self.sum[tree_index] = arr[l];
+            return;
+        }
+        let mid = l + (r - l) / 2;
+        let (l_t_ind, r_t_ind) = (left_child(tree_index), right_child(tree_index));
+        self.build(l_t_ind, l, mid, arr);
+        self.build(r_t_ind, mid + 1, r, arr);
+        self.sum[tree_index] = self.sum[l_t_ind] + self.sum[r_t_ind];
+    }
+
+    fn apply(&mut self, tree_index: usize, size: usize, v: i64) {
+        self.sum[tree_index] = v * size as i64;
+        self.assign[tree_index] = Some(v);
+    }
+
+    fn push_down(&mut self, tree_index: usize, l: usize, r: usize) {
+        if let Some(v) = self.assign[tree_index].take() {
+            let mid = l + (r - l) / 2;
+            self.apply(left_child(tree_index), mid - l + 1, v);
+            self.apply(right_child(tree_index), r - mid, v);
+        }
+    }
+
+    fn recursion_assign(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+        v: i64,
+    ) {
+        if query_left <= l && r <= query_right {
+            self.apply(tree_index, r - l + 1, v);
+            return;
+        }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let l_t_ind = left_child(tree_index);
+        let r_t_ind = right_child(tree_index);
+        if query_left <= mid {
+            self.recursion_assign(l_t_ind, l, mid, query_left, query_right, v);
+        }
+        if query_right > mid {
+            self.recursion_assign(r_t_ind, mid + 1, r, query_left, query_right, v);
+        }
+        self.sum[tree_index] = self.sum[l_t_ind] + self.sum[r_t_ind];
+    }
+
+    fn recursion_query(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+    ) -> i64 {
+        if query_left <= l && r <= query_right {
+            return self.sum[tree_index];
+        }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let mut res = 0;
+        if query_left <= mid {
+            res += self.recursion_query(left_child(tree_index), l, mid, query_left, query_right);
+        }
+        if query_right > mid {
+            res +=
+                self.recursion_query(right_child(tree_index), mid + 1, r, query_left, query_right);
+        }
+        res
+    }
+
+    fn check_range(&self, l: usize, r: usize) -> Result<(), QueryError> {
+        if r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(())
+    }
+
+    /// Sets every element in [l, r] to v.
+    pub fn range_assign(&mut self, l: usize, r: usize, v: i64) -> Result<(), QueryError> {
+        self.check_range(l, r)?;
+        self.recursion_assign(0, 0, self.len - 1, l, r, v);
+        Ok(())
+    }
+
+    /// Returns the sum of the elements in [l, r].
+    pub fn query(&mut self, l: usize, r: usize) -> Result<i64, QueryError> {
+        self.check_range(l, r)?;
+        Ok(self.recursion_query(0, 0, self.len - 1, l, r))
+    }
+}
+
+/// Min/max tree supporting lazy range assignment. Both aggregates share one
+/// pending assignment per node, which sets the node's minimum and maximum
+/// alike.
+pub struct RangeAssignMinMaxTree {
+    len: usize,
+    min: Vec<i64>,
+    max: Vec<i64>,
+    assign: Vec<Option<i64>>,
+}
+
+impl RangeAssignMinMaxTree {
+    pub fn new(arr: Vec<i64>) -> RangeAssignMinMaxTree {
+        let mut tree = Self {
+            len: arr.len(),
+            min: vec![0; 4 * arr.len()],
+            max: vec![0; 4 * arr.len()],
+            assign: vec![None; 4 * arr.len()],
+        };
+        if !arr.is_empty() {
+            tree.build(0, 0, arr.len() - 1, &arr);
+        }
+        tree
+    }
+
+    fn build(&mut self, tree_index: usize, l: usize, r: usize, arr: &[i64]) {
+        if l == r {
+            self.min[tree_index] = arr[l];
+            self.max[tree_index] = arr[l];
+            return;
+        }
+        let mid = l + (r - l) / 2;
+        self.build(left_child(tree_index), l, mid, arr);
+        self.build(right_child(tree_index), mid + 1, r, arr);
+        self.pull_up(tree_index);
+    }
+
+    fn pull_up(&mut self, tree_index: usize) {
+        let (l_t_ind, r_t_ind) = (left_child(tree_index), right_child(tree_index));
+        self.min[tree_index] = self.min[l_t_ind].min(self.min[r_t_ind]);
+        self.max[tree_index] = self.max[l_t_ind].max(self.max[r_t_ind]);
+    }
+
+    fn apply(&mut self, tree_index: usize, v: i64) {
+        self.min[tree_index] = v;
+        self.max[tree_index] = v;
+        self.assign[tree_index] = Some(v);
+    }
+
+    fn push_down(&mut self, tree_index: usize) {
+        if let Some(v) = self.assign[tree_index].take() {
+            self.apply(left_child(tree_index), v);
+            self.apply(right_child(tree_index), v);
+        }
+    }
+
+    fn recursion_assign(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+        v: i64,
+    ) {
+        if query_left <= l && r <= query_right {
+            self.apply(tree_index, v);
+            return;
+        }
+        self.push_down(tree_index);
+        let mid = l + (r - l) / 2;
+        if query_left <= mid {
+            self.recursion_assign(left_child(tree_index), l, mid, query_left, query_right, v);
+        }
+        if query_right > mid {
+            self.recursion_assign(
+                right_child(tree_index),
+                mid + 1,
+                r,
+                query_left,
+                query_right,
+                v,
+            );
+        }
+        self.pull_up(tree_index);
+    }
+
+    /// Returns the minimum and maximum of the elements in [query_left, query_right].
+    fn recursion_query(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+    ) -> (i64, i64) {
+        if query_left <= l && r <= query_right {
+            return (self.min[tree_index], self.max[tree_index]);
+        }
+        self.push_down(tree_index);
+        let mid = l + (r - l) / 2;
+        let mut res = (i64::MAX, i64::MIN);
+        if query_left <= mid {
+            let (lo, hi) =
+                self.recursion_query(left_child(tree_index), l, mid, query_left, query_right);
+            res = (res.0.min(lo), res.1.max(hi));
+        }
+        if query_right > mid {
+            let (lo, hi) =
+                self.recursion_query(right_child(tree_index), mid + 1, r, query_left, query_right);
+            res = (res.0.min(lo), res.1.max(hi));
+        }
+        res
+    }
+
+    fn check_range(&self, l: usize, r: usize) -> Result<(), QueryError> {
+        if r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(())
+    }
+
+    /// Sets every element in [l, r] to v.
+    pub fn range_assign(&mut self, l: usize, r: usize, v: i64) -> Result<(), QueryError> {
+        self.check_range(l, r)?;
+        self.recursion_assign(0, 0, self.len - 1, l, r, v);
+        Ok(())
+    }
+
+    /// Returns the smallest element in [l, r].
+    pub fn query_min(&mut self, l: usize, r: usize) -> Result<i64, QueryError> {
+        self.check_range(l, r)?;
+        Ok(self.recursion_query(0, 0, self.len - 1, l, r).0)
+    }
+
+    /// Returns the largest element in [l, r].
+    pub fn query_max(&mut self, l: usize, r: usize) -> Result<i64, QueryError> {
+        self.check_range(l, r)?;
+        Ok(self.recursion_query(0, 0, self.len - 1, l, r).1)
+    }
+}
+
+/// Sum tree supporting both lazy range assignment and lazy range addition.
+/// A node holds at most one kind of pending tag: an assignment clears the
+/// pending addition below it, and an addition on top of a pending
+/// assignment is folded into the assigned value.
+pub struct AssignAddSumSegmentTree {
+    len: usize,
+    sum: Vec<i64>,
+    assign: Vec<Option<i64>>,
+    add: Vec<i64>,
+}
+
+impl AssignAddSumSegmentTree {
+    pub fn new(arr: Vec<i64>) -> AssignAddSumSegmentTree {
+        let mut tree = Self {
+            len: arr.len(),
+            sum: vec![0; 4 * arr.len()],
+            assign: vec![None; 4 * arr.len()],
+            add: vec![0; 4 * arr.len()],
+        };
+        if !arr.is_empty() {
+            tree.build(0, 0, arr.len() - 1, &arr);
+        }
+        tree
+    }
+
+    fn build(&mut self, tree_index: usize, l: usize, r: usize, arr: &[i64]) {
+        if l == r {
+            self.sum[tree_index] = arr[l];
+            return;
+        }
+        let mid = l + (r - l) / 2;
+        let (l_t_ind, r_t_ind) = (left_child(tree_index), right_child(tree_index));
+        self.build(l_t_ind, l, mid, arr);
+        self.build(r_t_ind, mid + 1, r, arr);
+        self.sum[tree_index] = self.sum[l_t_ind] + self.sum[r_t_ind];
+    }
+
+    fn apply_assign(&mut self, tree_index: usize, size: usize, v: i64) {
+        self.sum[tree_index] = v * size as i64;
+        self.assign[tree_index] = Some(v);
+        self.add[tree_index] = 0;
+    }
+
+    fn apply_add(&mut self, tree_index: usize, size: usize, delta: i64) {
+        self.sum[tree_index] += delta * size as i64;
+        match self.assign[tree_index] {
+            Some(ref mut v) => *v += delta,
+            None => self.add[tree_index] += delta,
+        }
+    }
+
+    fn push_down(&mut self, tree_index: usize, l: usize, r: usize) {
+        let mid = l + (r - l) / 2;
+        let (l_t_ind, r_t_ind) = (left_child(tree_index), right_child(tree_index));
+        if let Some(v) = self.assign[tree_index].take() {
+            self.apply_assign(l_t_ind, mid - l + 1, v);
+            self.apply_assign(r_t_ind, r - mid, v);
+        }
+        let delta = core::mem::take(&mut self.add[tree_index]);
+        if delta != 0 {
+            self.apply_add(l_t_ind, mid - l + 1, delta);
+            self.apply_add(r_t_ind, r - mid, delta);
+        }
+    }
+
+    /// Applies `assign` if it is Some, otherwise adds `delta`, to every
+    /// element of [query_left, query_right].
+    #[allow(clippy::too_many_arguments)]
+    fn recursion_update(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+        assign: Option<i64>,
+        delta: i64,
+    ) {
+        if query_left <= l && r <= query_right {
+            match assign {
+                Some(v) => self.apply_assign(tree_index, r - l + 1, v),
+                None => self.apply_add(tree_index, r - l + 1, delta),
+            }
+            return;
+        }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let l_t_ind = left_child(tree_index);
+        let r_t_ind = right_child(tree_index);
+        if query_left <= mid {
+            self.recursion_update(l_t_ind, l, mid, query_left, query_right, assign, delta);
+        }
+        if query_right > mid {
+            self.recursion_update(r_t_ind, mid + 1, r, query_left, query_right, assign, delta);
+        }
+        self.sum[tree_index] = self.sum[l_t_ind] + self.sum[r_t_ind];
+    }
+
+    fn recursion_query(
+        &mut self,
+        tree_index: usize,
+        l: usize,
+        r: usize,
+        query_left: usize,
+        query_right: usize,
+    ) -> i64 {
+        if query_left <= l && r <= query_right {
+            return self.sum[tree_index];
+        }
+        self.push_down(tree_index, l, r);
+        let mid = l + (r - l) / 2;
+        let mut res = 0;
+        if query_left <= mid {
+            res += self.recursion_query(left_child(tree_index), l, mid, query_left, query_right);
+        }
+        if query_right > mid {
+            res +=
+                self.recursion_query(right_child(tree_index), mid + 1, r, query_left, query_right);
+        }
+        res
+    }
+
+    fn check_range(&self, l: usize, r: usize) -> Result<(), QueryError> {
+        if r >= self.len {
+            return Err(QueryError::OutOfBounds);
+        }
+        if l > r {
+            return Err(QueryError::InvalidRange);
+        }
+        Ok(())
+    }
+
+    /// Sets every element in [l, r] to v, discarding earlier additions.
+    pub fn range_assign(&mut self, l: usize, r: usize, v: i64) -> Result<(), QueryError> {
+        self.check_range(l, r)?;
+        self.recursion_update(0, 0, self.len - 1, l, r, Some(v), 0);
+        Ok(())
+    }
+
+    /// Adds delta to every element in [l, r].
+    pub fn range_add(&mut self, l: usize, r: usize, delta: i64) -> Result<(), QueryError> {
+        self.check_range(l, r)?;
+        self.recursion_update(0, 0, self.len - 1, l, r, None, delta);
+        Ok(())
+    }
+
+    /// Returns the sum of the elements in [l, r].
+    pub fn query(&mut self, l: usize, r: usize) -> Result<i64, QueryError> {
+        self.check_range(l, r)?;
+        Ok(self.recursion_query(0, 0, self.len - 1, l, r))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_on_change() {
+        let mut tree = SegmentTree::new_segment_tree(vec![1, 2, 3, 4]);
+        tree.build();
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let log = changes.clone();
+        tree.on_change(move |i, v| log.borrow_mut().push((i, v)));
+
+        tree.set(2, 7).unwrap();
+        tree.set(0, 1).unwrap();
+        tree.set(3, 5).unwrap();
+        tree.set(2, 7).unwrap();
+        tree.set(1, 0).unwrap();
+
+        assert_eq!(*changes.borrow(), vec![(2, 7), (3, 5), (1, 0)]);
+    }
+
+    #[test]
+    fn test_values() {
+        let input = vec![5, -3, 8, 0, 2, 9];
+        let tree = SegmentTree::new_segment_tree(input.clone());
+
+        assert_eq!(tree.values(1, 4), Some(&input[1..=4]));
+        assert_eq!(tree.values(0, 5), Some(&input[..]));
+        assert_eq!(tree.values(3, 6), None);
+        assert_eq!(tree.values(4, 2), None);
+    }
+
+    #[test]
+    fn test_mod_segment_tree() {
+        const M: u64 = 1_000_000_007;
+        let mut rng = crate::rng::SmallRng::new(111);
+        let n = 37;
+        let mut naive: Vec<u64> = (0..n).map(|_| rng.next_u64() % M).collect();
+        let mut tree = ModSegmentTree::new(naive.clone(), M);
+
+        for _ in 0..2000 {
+            let a = rng.next_u32() as usize % n;
+            let b = rng.next_u32() as usize % n;
+            let (l, r) = (a.min(b), a.max(b));
+            let v = rng.next_u64();
+            match rng.next_u32() % 3 {
+                0 => {
+                    tree.range_add(l, r, v).unwrap();
+                    for x in &mut naive[l..=r] {
+                        *x = (*x + v % M) % M;
+                    }
+                }
+                1 => {
+                    tree.range_mul(l, r, v).unwrap();
+                    for x in &mut naive[l..=r] {
+                        *x = *x * (v % M) % M;
+                    }
+                }
+                _ => {
+                    let expected = naive[l..=r].iter().fold(0, |acc, x| (acc + x) % M);
+                    assert_eq!(tree.query(l, r), Ok(expected));
+                }
+            }
+        }
+        assert!(tree.query(0, n).is_err());
+    }
+
+    #[test]
+    fn test_assign_sum_segment_tree() {
+        let mut tree = AssignSumSegmentTree::new(vec![1, 1, 1, 1, 1]);
+        tree.range_assign(1, 3, 5).unwrap();
+        assert_eq!(tree.query(0, 4), Ok(17));
+        assert_eq!(tree.query(3, 4), Ok(6));
+        assert_eq!(tree.range_assign(2, 5, 0), Err(QueryError::OutOfBounds));
+
+        let mut rng = crate::rng::SmallRng::new(151);
+        let n = 29;
+        let mut naive: Vec<i64> = (0..n).map(|_| i64::from(rng.next_u32() % 100)).collect();
+        let mut tree = AssignSumSegmentTree::new(naive.clone());
+        for _ in 0..2000 {
+            let a = rng.next_u32() as usize % n;
+            let b = rng.next_u32() as usize % n;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.next_u32() & 1 == 0 {
+                let v = i64::from(rng.next_u32() % 100) - 50;
+                tree.range_assign(l, r, v).unwrap();
+                for x in &mut naive[l..=r] {
+                    *x = v;
+                }
+            } else {
+                assert_eq!(tree.query(l, r), Ok(naive[l..=r].iter().sum()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_assign_min_max_tree() {
+        let mut tree = RangeAssignMinMaxTree::new(vec![4, 4, 4, 4, 4]);
+        tree.range_assign(1, 3, 5).unwrap();
+        assert_eq!(tree.query_min(0, 4), Ok(4));
+        assert_eq!(tree.query_max(0, 4), Ok(5));
+        assert_eq!(tree.query_min(1, 3), Ok(5));
+        assert_eq!(tree.query_max(4, 4), Ok(4));
+        assert_eq!(tree.query_min(3, 1), Err(QueryError::InvalidRange));
+        assert_eq!(tree.range_assign(0, 5, 1), Err(QueryError::OutOfBounds));
+
+        let mut rng = crate::rng::SmallRng::new(185);
+        let n = 31;
+        let mut naive: Vec<i64> = (0..n).map(|_| i64::from(rng.next_u32() % 100)).collect();
+        let mut tree = RangeAssignMinMaxTree::new(naive.clone());
+        for _ in 0..2000 {
+            let a = rng.next_u32() as usize % n;
+            let b = rng.next_u32() as usize % n;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.next_u32() & 1 == 0 {
+                let v = i64::from(rng.next_u32() % 100) - 50;
+                tree.range_assign(l, r, v).unwrap();
+                for x in &mut naive[l..=r] {
+                    *x = v;
+                }
+            } else {
+                assert_eq!(
+                    tree.query_min(l, r).ok(),
+                    naive[l..=r].iter().min().cloned()
+                );
+                assert_eq!(
+                    tree.query_max(l, r).ok(),
+                    naive[l..=r].iter().max().cloned()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_assign_add_sum_segment_tree() {
+        let mut tree = AssignAddSumSegmentTree::new(vec![1, 2, 3, 4, 5]);
+        tree.range_add(0, 4, 1).unwrap();
+        assert_eq!(tree.query(0, 4), Ok(20));
+        tree.range_assign(1, 3, 10).unwrap();
+        assert_eq!(tree.query(0, 4), Ok(38));
+        tree.range_add(2, 4, 5).unwrap();
+        assert_eq!(tree.query(0, 4), Ok(53));
+        assert_eq!(tree.query(1, 1), Ok(10));
+        assert_eq!(tree.query(3, 3), Ok(15));
+        tree.range_assign(0, 4, 0).unwrap();
+        assert_eq!(tree.query(0, 4), Ok(0));
+        assert_eq!(tree.range_add(0, 5, 1), Err(QueryError::OutOfBounds));
+        assert_eq!(tree.query(2, 1), Err(QueryError::InvalidRange));
+
+        let mut rng = crate::rng::SmallRng::new(204);
+        for &n in [1, 2, 5, 37].iter() {
+            let mut naive: Vec<i64> = (0..n).map(|_| i64::from(rng.next_u32() % 100)).collect();
+            let mut tree = AssignAddSumSegmentTree::new(naive.clone());
+            for _ in 0..3000 {
+                let a = rng.next_u32() as usize % n;
+                let b = rng.next_u32() as usize % n;
+                let (l, r) = (a.min(b), a.max(b));
+                let v = i64::from(rng.next_u32() % 100) - 50;
+                match rng.next_u32() % 3 {
+                    0 => {
+                        tree.range_assign(l, r, v).unwrap();
+                        naive[l..=r].iter_mut().for_each(|x| *x = v);
+                    }
+                    1 => {
+                        tree.range_add(l, r, v).unwrap();
+                        naive[l..=r].iter_mut().for_each(|x| *x += v);
+                    }
+                    _ => assert_eq!(tree.query(l, r), Ok(naive[l..=r].iter().sum())),
+                }
+            }
+            for (i, &x) in naive.iter().enumerate() {
+                assert_eq!(tree.query(i, i), Ok(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_many() {
+        let mut tree = SegmentTree::new_segment_tree(vec![4, -1, 7, 3, 0, 2]);
+        tree.build();
+        let ranges = [(0, 5), (2, 2), (1, 4), (3, 1), (0, 9)];
+
+        let answers = tree.query_many(&ranges);
+        let expected: Vec<_> = ranges.iter().map(|&(l, r)| tree.query(l, r)).collect();
+        assert_eq!(answers, expected);
+        assert_eq!(answers[0], Ok(15));
+        assert_eq!(answers[3], Err(QueryError::InvalidRange));
+        assert_eq!(answers[4], Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_random_ops_match_naive() {
+        let mut rng = crate::rng::SmallRng::new(137);
+        for n in 1..=40 {
+            let mut naive: Vec<i32> = (0..n).map(|_| rng.next_u32() as i32 % 1000).collect();
+            let mut tree = SegmentTree::new_segment_tree(naive.clone());
+            tree.build();
+            for _ in 0..250 {
+                let a = rng.next_u32() as usize % n;
+                let b = rng.next_u32() as usize % n;
+                if rng.next_u32() & 1 == 0 {
+                    let v = rng.next_u32() as i32 % 1000;
+                    naive[a] = v;
+                    tree.set(a, v).unwrap();
+                } else {
+                    let (l, r) = (a.min(b), a.max(b));
+                    let expected: i32 = naive[l..=r].iter().sum();
+                    assert_eq!(tree.query(l, r), Ok(expected), "n={}, [{}, {}]", n, l, r);
+                }
+            }
+            assert_eq!(tree.values(0, n - 1), Some(&naive[..]));
+        }
+    }
+
+    #[test]
+    fn test_query_with() {
+        let mut tree = SegmentTree::new_segment_tree(vec![4, -2, 9, 7, -5, 3]);
+        tree.build();
+        assert_eq!(tree.query_with(0, 5, i32::max, i32::MIN), Ok(9));
+        assert_eq!(tree.query_with(3, 5, i32::max, i32::MIN), Ok(7));
+        assert_eq!(tree.query_with(1, 4, i32::min, i32::MAX), Ok(-5));
+        assert_eq!(tree.query_with(0, 5, |a, b| a + b, 0), tree.query(0, 5));
+        assert_eq!(
+            tree.query_with(2, 6, i32::max, i32::MIN),
+            Err(QueryError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_from_records() {
+        #[derive(Debug, PartialEq)]
+        struct Player {
+            name: &'static str,
+            score: i32,
+        }
+
+        let players = vec![
+            Player {
+                name: "ann",
+                score: 12,
+            },
+            Player {
+                name: "bob",
+                score: 7,
+            },
+            Player {
+                name: "cyd",
+                score: 30,
+            },
+            Player {
+                name: "dee",
+                score: 1,
+            },
+        ];
+        let mut tree = SegmentTree::from_records(players, |p: &Player| p.score);
+        assert_eq!(tree.query(0, 3), Ok(50));
+        assert_eq!(tree.query(1, 2), Ok(37));
+        assert_eq!(tree.get(2).map(|p| p.name), Some("cyd"));
+        assert_eq!(tree.get(4), None);
+
+        let old = tree
+            .set(
+                1,
+                Player {
+                    name: "bea",
+                    score: 20,
+                },
+            )
+            .unwrap();
+        assert_eq!(old.name, "bob");
+        assert_eq!(tree.query(0, 1), Ok(32));
+        assert_eq!(
+            tree.get(1),
+            Some(&Player {
+                name: "bea",
+                score: 20
+            })
+        );
+        assert!(tree
+            .set(
+                4,
+                Player {
+                    name: "eve",
+                    score: 0
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_if() {
+        let mut tree = SegmentTree::new_from_fn(4, |i| 10 * i as i32);
+        let larger = |cur, new| new > cur;
+        assert_eq!(tree.update_if(2, 15, larger), Ok(false));
+        assert_eq!(tree.get(2), Some(20));
+        assert_eq!(tree.update_if(2, 25, larger), Ok(true));
+        assert_eq!(tree.get(2), Some(25));
+        assert_eq!(tree.query(0, 3), Ok(65));
+        assert_eq!(tree.update_if(2, 25, larger), Ok(false));
+        assert_eq!(tree.update_if(4, 99, larger), Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_query_bounds() {
+        let data = vec![3, 1, 4, 1, 5];
+        let len = data.len();
+        let mut tree = SegmentTree::new_segment_tree(data);
+        tree.build();
+        assert_eq!(tree.query(0, len - 1), Ok(14));
+        assert_eq!(tree.query(0, len), Err(QueryError::OutOfBounds));
+        assert_eq!(tree.query(len, len), Err(QueryError::OutOfBounds));
+        assert_eq!(tree.query(len - 1, len - 1), Ok(5));
+    }
+
+    #[test]
+    fn test_new_from_fn() {
+        let tree = SegmentTree::new_from_fn(5, |i| i as i32);
+        assert_eq!(tree.query(0, 4), Ok(10));
+        assert_eq!(tree.get(3), Some(3));
+
+        let squares = SegmentTree::new_from_fn(10, |i| (i * i) as u64);
+        assert_eq!(squares.query(2, 4), Ok(4 + 9 + 16));
+        assert_eq!(SegmentTree::new_from_fn(0, |i| i as i32).get(0), None);
+    }
+
+    #[test]
+    fn test_node_value() {
+        let mut tree = SegmentTree::new_segment_tree(vec![1, 2, 3, 4, 5]);
+        assert_eq!(tree.tree_len(), 20);
+        assert_eq!(tree.node_value(0), None);
+        tree.build();
+        assert_eq!(tree.node_value(0), Some(15));
+        // [0, 2] and [3, 4], then [0, 1] and [2, 2]
+        assert_eq!(tree.node_value(1), Some(6));
+        assert_eq!(tree.node_value(2), Some(9));
+        assert_eq!(tree.node_value(3), Some(3));
+        assert_eq!(tree.node_value(4), Some(3));
+        assert_eq!(tree.node_value(19), None);
+        assert_eq!(tree.node_value(20), None);
+        tree.set(4, 10).unwrap();
+        assert_eq!(tree.node_value(0), Some(20));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut tree = SegmentTree::new_from_fn(6, |i| i as i32 + 1);
+        let before = tree.snapshot();
+        tree.set(0, 100).unwrap();
+        tree.set(5, -6).unwrap();
+        let after = tree.snapshot();
+        let copy = before.clone();
+        tree.set(2, 0).unwrap();
+
+        assert_eq!(before.query(0, 5), Ok(21));
+        assert_eq!(copy.query(0, 2), Ok(6));
+        assert_eq!(before.get(0), Some(1));
+        assert_eq!(after.query(0, 5), Ok(100 + 2 + 3 + 4 + 5 - 6));
+        assert_eq!(tree.query(0, 5), Ok(100 + 2 + 4 + 5 - 6));
+        assert_eq!(before.query(3, 6), Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_count_value() {
+        let mut tree = SegmentTree::new_segment_tree(vec![1, 2, 2, 3, 2]);
+        assert_eq!(tree.count_value(0, 4, 2), Ok(3));
+        assert_eq!(tree.count_value(2, 3, 2), Ok(1));
+        assert_eq!(tree.count_value(0, 4, 7), Ok(0));
+        tree.build();
+        tree.set(0, 2).unwrap();
+        assert_eq!(tree.count_value(0, 4, 2), Ok(4));
+        assert_eq!(tree.count_value(3, 2, 2), Err(QueryError::InvalidRange));
+        assert_eq!(tree.count_value(0, 5, 2), Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let tree = SegmentTree::new_segment_tree(vec![7, 1, 3, 4, 10, 5, 3, -2]);
+        // lo = 1: [1, 4) [4, 7) [7, 10) [10, 13)
+        assert_eq!(tree.histogram(0, 6, 3), Ok(vec![3, 2, 1, 1]));
+        assert_eq!(tree.histogram(2, 3, 3), Ok(vec![2]));
+        assert_eq!(tree.histogram(5, 7, 1), Ok(vec![1, 0, 0, 0, 0, 1, 0, 1]));
+        assert_eq!(tree.histogram(4, 4, 100), Ok(vec![1]));
+        assert_eq!(tree.histogram(3, 1, 2), Err(QueryError::InvalidRange));
+        assert_eq!(tree.histogram(0, 8, 2), Err(QueryError::OutOfBounds));
+
+        let sparse = |counts: &[(usize, usize)]| Ok(counts.iter().cloned().collect());
+        assert_eq!(
+            tree.histogram_sparse(5, 7, 1),
+            sparse(&[(0, 1), (5, 1), (7, 1)])
+        );
+        assert_eq!(tree.histogram_sparse(0, 8, 2), Err(QueryError::OutOfBounds));
+
+        let extremes = SegmentTree::new_segment_tree(vec![i32::MIN, 0, i32::MAX]);
+        assert_eq!(extremes.histogram(0, 2, i32::MAX), Ok(vec![1, 1, 1]));
+        assert_eq!(
+            extremes.histogram_sparse(0, 2, 1),
+            sparse(&[(0, 1), (1 << 31, 1), (u32::MAX as usize, 1)])
+        );
+    }
+
+    #[test]
+    fn test_push_pop_resize() {
+        let mut tree = SegmentTree::new_segment_tree(vec![1, 2, 3]);
+        tree.build();
+        tree.push(4);
+        tree.push(5);
+        assert_eq!(tree.query(0, 4), Ok(15));
+        assert_eq!(tree.query(2, 4), Ok(12));
+        assert_eq!(tree.pop(), Some(5));
+        assert_eq!(tree.query(0, 3), Ok(10));
+        assert_eq!(tree.query(0, 4), Err(QueryError::OutOfBounds));
+
+        tree.resize(6, 7);
+        assert_eq!(tree.query(3, 5), Ok(18));
+        tree.resize(1, 0);
+        assert_eq!(tree.query(0, 0), Ok(1));
+        assert_eq!(tree.pop(), Some(1));
+        assert_eq!(tree.pop(), None);
+        tree.push(9);
+        assert_eq!(tree.query(0, 0), Ok(9));
+
+        let mut lazy = SegmentTreeBuilder::new()
+            .data(vec![5, 1, 4])
+            .op(Op::Max)
+            .with_lazy(true)
+            .build();
+        lazy.range_add(0, 2, 10).unwrap();
+        lazy.push(12);
+        assert_eq!(lazy.query(0, 3), Ok(15));
+        lazy.range_add(3, 3, 10).unwrap();
+        assert_eq!(lazy.pop(), Some(22));
+        assert_eq!(lazy.query(1, 2), Ok(14));
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut tree = SegmentTreeBuilder::new()
+            .data(vec![3, 1, 4, 1, 5, 9, 2, 6])
+            .op(Op::Max)
+            .with_lazy(true)
+            .build();
+        assert_eq!(tree.query(0, 3), Ok(4));
+        tree.range_add(1, 3, 10).unwrap();
+        assert_eq!(tree.query(0, 3), Ok(14));
+        assert_eq!(tree.query(3, 7), Ok(11));
+        assert_eq!(tree.query(0, 0), Ok(3));
+        assert_eq!(tree.get(2), Some(14));
+        tree.set(2, 0).unwrap();
+        assert_eq!(tree.query(0, 3), Ok(11));
+        assert_eq!(tree.range_add(0, 8, 1), Err(QueryError::OutOfBounds));
+
+        let min = SegmentTreeBuilder::new()
+            .data(vec![5, 2, 7])
+            .op(Op::Min)
+            .build();
+        assert_eq!(min.query(0, 2), Ok(2));
+        assert_eq!(min.query(2, 2), Ok(7));
+    }
+
+    #[test]
+    fn test_range_tree_trait_objects() {
+        let data = vec![3, 1, 4, 1, 5];
+        let mut sum = SegmentTree::new_segment_tree(data.clone());
+        sum.build();
+        let max = SegmentTreeBuilder::new().data(data).op(Op::Max).build();
+        let mut trees: Vec<Box<dyn RangeTree>> = vec![Box::new(sum), Box::new(max)];
+
+        let results: Vec<_> = trees.iter().map(|t| t.query(1, 3)).collect();
+        assert_eq!(results, vec![Ok(6), Ok(4)]);
+        for tree in trees.iter_mut() {
+            tree.update(2, 10).unwrap();
+        }
+        let results: Vec<_> = trees.iter().map(|t| t.query(0, 4)).collect();
+        assert_eq!(results, vec![Ok(20), Ok(10)]);
+        assert_eq!(trees[1].query(0, 5), Err(QueryError::OutOfBounds));
+        assert_eq!(trees[0].update(5, 1), Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_builder_lazy_random() {
+        let mut rng = crate::rng::SmallRng::new(174);
+        for &op in [Op::Sum, Op::Min, Op::Max].iter() {
+            let n = 50;
+            let mut naive: Vec<i64> = (0..n).map(|_| (rng.next_u32() % 100) as i64).collect();
+            let mut tree = SegmentTreeBuilder::new()
+                .data(naive.clone())
+                .op(op)
+                .with_lazy(true)
+                .build();
+            for _ in 0..500 {
+                let l = rng.next_u32() as usize % n;
+                let r = l + rng.next_u32() as usize % (n - l);
+                match rng.next_u32() % 3 {
+                    0 => {
+                        let delta = (rng.next_u32() % 21) as i64 - 10;
+                        tree.range_add(l, r, delta).unwrap();
+                        naive[l..=r].iter_mut().for_each(|x| *x += delta);
+                    }
+                    1 => {
+                        let value = (rng.next_u32() % 100) as i64;
+                        tree.set(l, value).unwrap();
+                        naive[l] = value;
+                    }
+                    _ => {
+                        let range = naive[l..=r].iter().cloned();
+                        let expected = match op {
+                            Op::Sum => range.sum(),
+                            Op::Min => range.min().unwrap(),
+                            Op::Max => range.max().unwrap(),
+                        };
+                        assert_eq!(tree.query(l, r), Ok(expected));
+                        assert_eq!(tree.get(r), Some(naive[r]));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks every lazy tree against a naive array after each operation,
+    /// over sizes that exercise unbalanced splits.
+    #[test]
+    fn test_lazy_push_down_random() {
+        let mut rng = crate::rng::SmallRng::new(192);
+        let range = |rng: &mut crate::rng::SmallRng, n: usize| {
+            let l = rng.next_u32() as usize % n;
+            (l, l + rng.next_u32() as usize % (n - l))
+        };
+        for &n in [1, 2, 3, 7, 16, 33, 100].iter() {
+            for &op in [Op::Sum, Op::Min, Op::Max].iter() {
+                let mut naive: Vec<i64> = (0..n).map(|_| (rng.next_u32() % 100) as i64).collect();
+                let mut tree = SegmentTreeBuilder::new()
+                    .data(naive.clone())
+                    .op(op)
+                    .with_lazy(true)
+                    .build();
+                let fold = |v: &[i64]| match op {
+                    Op::Sum => v.iter().sum(),
+                    Op::Min => *v.iter().min().unwrap(),
+                    Op::Max => *v.iter().max().unwrap(),
+                };
+                for _ in 0..3000 {
+                    let (l, r) = range(&mut rng, n);
+                    if rng.next_u32() & 1 == 0 {
+                        let delta = (rng.next_u32() % 41) as i64 - 20;
+                        tree.range_add(l, r, delta).unwrap();
+                        naive[l..=r].iter_mut().for_each(|x| *x += delta);
+                    } else {
+                        let value = (rng.next_u32() % 200) as i64 - 100;
+                        tree.set(l, value).unwrap();
+                        naive[l] = value;
+                    }
+                    let (ql, qr) = range(&mut rng, n);
+                    assert_eq!(tree.query(ql, qr), Ok(fold(&naive[ql..=qr])));
+                    assert_eq!(tree.query(0, n - 1), Ok(fold(&naive)));
+                    assert_eq!(tree.get(ql), Some(naive[ql]));
+                }
+            }
+
+            let mut naive: Vec<i64> = (0..n).map(|_| (rng.next_u32() % 100) as i64).collect();
+            let mut sum_tree = AssignSumSegmentTree::new(naive.clone());
+            let mut min_max_tree = RangeAssignMinMaxTree::new(naive.clone());
+            for _ in 0..3000 {
+                let (l, r) = range(&mut rng, n);
+                let value = (rng.next_u32() % 200) as i64 - 100;
+                sum_tree.range_assign(l, r, value).unwrap();
+                min_max_tree.range_assign(l, r, value).unwrap();
+                naive[l..=r].iter_mut().for_each(|x| *x = value);
+
+                let (ql, qr) = range(&mut rng, n);
+                let expected = &naive[ql..=qr];
+                assert_eq!(sum_tree.query(ql, qr), Ok(expected.iter().sum()));
+                assert_eq!(
+                    min_max_tree.query_min(ql, qr).ok(),
+                    expected.iter().min().cloned()
+                );
+                assert_eq!(
+                    min_max_tree.query_max(ql, qr).ok(),
+                    expected.iter().max().cloned()
+                );
+                assert_eq!(sum_tree.query(ql, ql), Ok(naive[ql]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_diff() {
+        let tree = SegmentTree::from_diff(vec![1, 2, -1, 3]);
+        assert_eq!(tree.values(0, 3), Some(&[1, 3, 2, 5][..]));
+        assert_eq!(tree.query(0, 3), Ok(11));
+        assert_eq!(tree.query(1, 2), Ok(5));
+        assert_eq!(SegmentTree::<i32>::from_diff(vec![]).get(0), None);
+    }
+
+    #[test]
+    fn test_saturating_segment_tree() {
+        let mut tree = SegmentTree::new_saturating(vec![i32::MAX; 5]);
+        assert_eq!(tree.query(0, 4), Ok(i32::MAX));
+        assert_eq!(tree.query(2, 2), Ok(i32::MAX));
+        tree.set(0, i32::MIN).unwrap();
+        assert_eq!(tree.query(0, 1), Ok(-1));
+        for i in 1..5 {
+            tree.set(i, i32::MIN).unwrap();
+        }
+        assert_eq!(tree.query(0, 4), Ok(i32::MIN));
+
+        let small = SegmentTree::new_saturating(vec![1, -2, 3]);
+        assert_eq!(small.query(0, 2), Ok(2));
+    }
+
+    #[test]
+    fn test_xor_segment_tree() {
+        let mut tree = SegmentTree::new_xor(vec![1, 2, 3]);
+        assert_eq!(tree.query(0, 2), Ok(0));
+        assert_eq!(tree.query(1, 2), Ok(1));
+        tree.set(1, 6).unwrap();
+        assert_eq!(tree.query(0, 2), Ok(1 ^ 6 ^ 3));
+        assert_eq!(tree.query(0, 1), Ok(7));
+        assert_eq!(tree.query(1, 3), Err(QueryError::OutOfBounds));
+
+        let mut rng = crate::rng::SmallRng::new(154);
+        let mut arr: Vec<u32> = (0..200).map(|_| rng.next_u32()).collect();
+        let mut tree = SegmentTree::new_xor(arr.clone());
+        for _ in 0..500 {
+            let i = rng.next_u32() as usize % arr.len();
+            arr[i] = rng.next_u32();
+            tree.set(i, arr[i]).unwrap();
+            let l = rng.next_u32() as usize % arr.len();
+            let r = l + rng.next_u32() as usize % (arr.len() - l);
+            let expected = arr[l..=r].iter().fold(0, |acc, &x| acc ^ x);
+            assert_eq!(tree.query(l, r), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_compact_segment_tree() {
+        let mut rng = crate::rng::SmallRng::new(121);
+        let n = 1000;
+        let arr: Vec<i32> = (0..n)
+            .map(|_| (rng.next_u32() % 2001) as i32 - 1000)
+            .collect();
+        let mut tree = SegmentTree::new_segment_tree(arr.clone());
+        tree.build();
+        let mut compact = CompactSegmentTree::new(arr);
+        assert_eq!(tree.tree.len(), 4000);
+        assert_eq!(compact.tree.len(), 2048);
+
+        for _ in 0..2000 {
+            let a = rng.next_u32() as usize % n;
+            let b = rng.next_u32() as usize % n;
+            if rng.next_u32() & 1 == 0 {
+                let v = (rng.next_u32() % 2001) as i32 - 1000;
+                tree.set(a, v).unwrap();
+                compact.set(a, v).unwrap();
+            } else {
+                let (l, r) = (a.min(b), a.max(b));
+                assert_eq!(compact.query(l, r), tree.query(l, r));
+            }
+        }
+        assert_eq!(compact.get(n - 1), tree.get(n - 1));
+        assert_eq!(compact.get(n), None);
+        assert_eq!(compact.query(0, n), Err(QueryError::OutOfBounds));
+        assert_eq!(compact.query(5, 4), Err(QueryError::InvalidRange));
+    }
+
+    #[test]
+    fn test_min_count() {
+        let mut tree = MinCountSegmentTree::new(vec![2, 1, 1, 3, 1]);
+        assert_eq!(tree.query_min_count(0, 4), Some((1, 3)));
+        assert_eq!(tree.query_min_count(0, 0), Some((2, 1)));
+        assert_eq!(tree.query_min_count(2, 3), Some((1, 1)));
+        assert_eq!(tree.query_min_count(3, 2), None);
+        assert_eq!(tree.query_min_count(0, 5), None);
+
+        tree.set(1, 0).unwrap();
+        assert_eq!(tree.query_min_count(0, 4), Some((0, 1)));
+        assert_eq!(tree.query_min_count(2, 4), Some((1, 2)));
+        assert_eq!(tree.set(5, 0), Err(QueryError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_f64_sums() {
+        let mut tree = SegmentTree::new_segment_tree(vec![0.5, 1.25, -2.0, 4.0]);
+        tree.build();
+        assert_eq!(tree.query(0, 3), Ok(3.75));
+        tree.set(2, 0.25).unwrap();
+        assert_eq!(tree.query(1, 2), Ok(1.5));
+    }
+
+    #[test]
+    fn test_u64_sums() {
+        let big = 1u64 << 40;
+        let mut tree = SegmentTree::new_segment_tree(vec![big, big, 3, big]);
+        tree.build();
+        assert_eq!(tree.query(0, 3), Ok(3 * big + 3));
+        tree.set(2, big).unwrap();
+        assert_eq!(tree.query(1, 3), Ok(3 * big));
+    }
+
+    #[cfg(feature = "num-traits")]
+    #[test]
+    fn test_wrapping_sums() {
+        use core::num::Wrapping;
+
+        let mut tree =
+            SegmentTree::new_segment_tree(vec![Wrapping(u8::MAX), Wrapping(2), Wrapping(3)]);
+        tree.build();
+        assert_eq!(tree.query(0, 1), Ok(Wrapping(1)));
+        assert_eq!(tree.query(0, 2), Ok(Wrapping(4)));
     }
 }
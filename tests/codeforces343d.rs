@@ -2,6 +2,7 @@
 //! To make a self-contained file for contest submission, dump each desired
 //! module's contents directly here instead of the use statements.
 //! Also, use the commented code in main() to employ standard I/O.
+#![cfg(feature = "std")]
 extern crate contest_algorithms;
 use contest_algorithms::graph::Graph;
 use contest_algorithms::range_query::{specs::AssignSum, StaticArq};
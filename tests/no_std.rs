@@ -0,0 +1,19 @@
+//! Exercises what remains of the crate without the `std` feature. Run with
+//! `cargo test --no-default-features`.
+#![cfg(not(feature = "std"))]
+use contest_algorithms::graph::ring_buffer::{RingBuffer, RingBufferMode};
+use contest_algorithms::range_query::seg_tree::SegmentTree;
+
+#[test]
+fn test_no_std_core() {
+    let mut rb = RingBuffer::new(2, RingBufferMode::Override);
+    for i in 0..5 {
+        assert!(rb.write(i).is_ok());
+    }
+    assert_eq!(rb.to_vec(), vec![3, 4]);
+
+    let mut tree = SegmentTree::new_from_fn(4, |i| i as i32);
+    assert_eq!(tree.query(0, 3), Ok(6));
+    tree.set(0, 10).unwrap();
+    assert_eq!(tree.query(0, 1), Ok(11));
+}